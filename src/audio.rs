@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use rodio::buffer::SamplesBuffer;
 use rodio::{Decoder, OutputStreamBuilder, Sample, Sink, Source};
 use std::fs;
@@ -8,10 +8,12 @@ use std::process::Command;
 use std::sync::mpsc::{self, Sender};
 use std::sync::{Mutex, OnceLock};
 
-use crate::config::{Config, Mode, SoundChoice};
+use crate::backend::{apply, is_pending, read_state, resolve_pending_sources, Desired, MicState};
+use crate::config::{target_label, Config, MicTarget, Mode, SoundChoice};
 use crate::constants::{
     DEFAULT_SOUND_OFF_EVENT, DEFAULT_SOUND_OFF_WAV, DEFAULT_SOUND_ON_EVENT, DEFAULT_SOUND_ON_WAV,
 };
+use crate::notify::{notify, Category};
 
 #[derive(Clone)]
 struct PlayRequest {
@@ -142,28 +144,6 @@ fn send_samples(samples: SamplesBuffer, volume: f32) {
     let _ = sender.send(PlayRequest { samples, volume });
 }
 
-/// Set the default microphone volume to an absolute level.
-pub(crate) fn set_volume(level: f32) -> Result<()> {
-    Command::new("wpctl")
-        .args(["set-volume", "@DEFAULT_SOURCE@", &format!("{level}")])
-        .status()
-        .context("wpctl failed")?;
-    Ok(())
-}
-
-/// Mute or unmute the default microphone source.
-pub(crate) fn set_mute(muted: bool) -> Result<()> {
-    Command::new("wpctl")
-        .args([
-            "set-mute",
-            "@DEFAULT_SOURCE@",
-            if muted { "1" } else { "0" },
-        ])
-        .status()
-        .context("wpctl failed")?;
-    Ok(())
-}
-
 /// Play a user-supplied audio file (mp3/wav/ogg). Best-effort, async.
 fn play_sound_file(path: PathBuf, volume: f32) {
     if let Ok(bytes) = fs::read(&path) {
@@ -182,7 +162,7 @@ fn try_play_embedded_sound(on: bool, volume: f32) -> bool {
     false
 }
 
-fn find_bin(name: &str) -> Option<PathBuf> {
+pub(crate) fn find_bin(name: &str) -> Option<PathBuf> {
     if let Ok(path) = which::which(name) {
         return Some(path);
     }
@@ -248,20 +228,49 @@ fn play_default_sound(on: bool, volume: f32) {
     });
 }
 
-/// Apply the "mic on" action according to the selected mode.
-pub(crate) fn apply_on(config: &Config) -> Result<()> {
-    match config.mode {
-        Mode::Volume => set_volume(config.on_level),
-        Mode::Mute => set_mute(false),
+fn desired_state(target: &MicTarget, on: bool) -> Desired {
+    match target.mode {
+        Mode::Volume if on => Desired::Volume(target.on_level),
+        Mode::Volume => Desired::Volume(target.off_level),
+        Mode::Mute => Desired::Muted(!on),
     }
 }
 
-/// Apply the "mic off" action according to the selected mode.
-pub(crate) fn apply_off(config: &Config) -> Result<()> {
-    match config.mode {
-        Mode::Volume => set_volume(config.off_level),
-        Mode::Mute => set_mute(true),
+/// Apply the on/off transition to every configured source.
+///
+/// Failures are reported per source; the transition only fails if no source
+/// could be updated. Sources that are not found yet get the state once they appear.
+fn apply_targets(config: &Config, on: bool) -> Result<()> {
+    let mut attempted = 0;
+    let mut failures = 0;
+    for (index, target) in config.targets.iter().enumerate() {
+        if is_pending(index) {
+            continue;
+        }
+        attempted += 1;
+        if let Err(err) = apply(index, desired_state(target, on)) {
+            eprintln!(
+                "Failed to turn mic {} on source {}: {err:#}",
+                if on { "on" } else { "off" },
+                target_label(target)
+            );
+            failures += 1;
+        }
+    }
+    if failures > 0 && failures == attempted {
+        bail!("Failed to update any mic source");
     }
+    Ok(())
+}
+
+/// Apply the "mic on" action to every configured source.
+pub(crate) fn apply_on(config: &Config) -> Result<()> {
+    apply_targets(config, true)
+}
+
+/// Apply the "mic off" action to every configured source.
+pub(crate) fn apply_off(config: &Config) -> Result<()> {
+    apply_targets(config, false)
 }
 
 pub(crate) fn play_transition_sound(config: &Config, on: bool) {
@@ -307,3 +316,105 @@ pub(crate) fn play_transition_sound(config: &Config, on: bool) {
         }
     }
 }
+
+/// Look up the sources that were missing and put those found into the current mic state.
+pub(crate) fn attach_new_sources(config: &Config, on: bool) {
+    for index in resolve_pending_sources() {
+        let Some(target) = config.targets.get(index) else {
+            continue;
+        };
+        if let Err(err) = apply(index, desired_state(target, on)) {
+            eprintln!(
+                "Failed to turn mic {} on source {}: {err:#}",
+                if on { "on" } else { "off" },
+                target_label(target)
+            );
+        }
+    }
+}
+
+/// Re-apply the expected state to sources that were changed outside pttkey.
+pub(crate) fn reconcile(config: &Config, on: bool) {
+    for (index, target) in config.targets.iter().enumerate() {
+        let desired = desired_state(target, on);
+        let Ok(state) = read_state(index) else {
+            continue;
+        };
+        if desired.matches(state) {
+            continue;
+        }
+        println!(
+            "Mic source {} changed outside pttkey, re-applying",
+            target_label(target)
+        );
+        if let Err(err) = apply(index, desired) {
+            eprintln!(
+                "Failed to restore mic state on source {}: {err:#}",
+                target_label(target)
+            );
+            notify(
+                config,
+                Category::Backend,
+                &format!("Failed to restore mic state on {}", target_label(target)),
+                &format!("{err:#}"),
+            );
+        }
+    }
+}
+
+/// Mic state of every configured source, captured before pttkey touches it.
+pub(crate) struct Snapshot(Vec<Option<MicState>>);
+
+/// Read the current state of every configured source.
+pub(crate) fn snapshot(config: &Config) -> Snapshot {
+    let states = config
+        .targets
+        .iter()
+        .enumerate()
+        .map(|(index, target)| match read_state(index) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                eprintln!(
+                    "Failed to read mic state of source {}, it will be muted on exit: {err:#}",
+                    target_label(target)
+                );
+                None
+            }
+        })
+        .collect();
+    Snapshot(states)
+}
+
+/// Put every source back into its snapshot state (or off if it had none).
+///
+/// Volume and mute are restored independently so one failing does not keep the other from
+/// being restored; in mute mode the volume pttkey never changed is left alone.
+pub(crate) fn restore(config: &Config, snapshot: &Snapshot) {
+    for (index, target) in config.targets.iter().enumerate() {
+        let Some(state) = snapshot.0.get(index).copied().flatten() else {
+            if let Err(err) = apply(index, desired_state(target, false)) {
+                eprintln!(
+                    "Failed to restore mic state on source {}: {err:#}",
+                    target_label(target)
+                );
+            }
+            continue;
+        };
+        let mut desired = vec![Desired::Muted(state.muted)];
+        if target.mode == Mode::Volume {
+            desired.insert(0, Desired::Volume(state.volume));
+        }
+        for desired in desired {
+            if let Err(err) = apply(index, desired) {
+                let what = match desired {
+                    Desired::Volume(_) => "volume",
+                    Desired::Muted(_) => "mute state",
+                };
+                eprintln!(
+                    "Failed to restore mic {what} on source {}: {err:#}",
+                    target_label(target)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Absolute axis bindings: foot pedals and analog triggers that count as held past a threshold.
+
+use evdev::{AbsoluteAxisCode, Device};
+use std::collections::{HashMap, HashSet};
+
+use crate::config::{AxisTrigger, Config};
+
+/// Axis ranges and pressed axis bindings of one input device.
+pub(crate) struct AxisState {
+    /// Minimum and maximum of each axis of the device.
+    ranges: HashMap<AbsoluteAxisCode, (i32, i32)>,
+    /// Indices of the bindings whose axis is pressed on this device.
+    engaged: HashSet<usize>,
+}
+
+impl AxisState {
+    /// Read the ranges and current positions so an axis held at startup counts at once.
+    pub(crate) fn new(config: &Config, device: &Device) -> Self {
+        let mut state = Self {
+            ranges: HashMap::new(),
+            engaged: HashSet::new(),
+        };
+        if !config.bindings.iter().any(|binding| binding.axis.is_some()) {
+            return state;
+        }
+        let Ok(absinfo) = device.get_absinfo() else {
+            return state;
+        };
+        let mut values = Vec::new();
+        for (axis, info) in absinfo {
+            state.ranges.insert(axis, (info.minimum(), info.maximum()));
+            values.push((axis, info.value()));
+        }
+        for (axis, value) in values {
+            state.update(config, axis, value);
+        }
+        state
+    }
+
+    /// Feed an axis event; returns whether a binding was pressed or released by it.
+    pub(crate) fn update(&mut self, config: &Config, axis: AbsoluteAxisCode, value: i32) -> bool {
+        let Some(position) = self.position(axis, value) else {
+            return false;
+        };
+        let mut changed = false;
+        for (index, binding) in config.bindings.iter().enumerate() {
+            let Some(trigger) = binding.axis.filter(|trigger| trigger.axis == axis) else {
+                continue;
+            };
+            let was_engaged = self.engaged.contains(&index);
+            if is_engaged(&trigger, position, was_engaged) != was_engaged {
+                if was_engaged {
+                    self.engaged.remove(&index);
+                } else {
+                    self.engaged.insert(index);
+                }
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Whether the axis of the binding at `index` is pressed on this device.
+    pub(crate) fn is_engaged(&self, index: usize) -> bool {
+        self.engaged.contains(&index)
+    }
+
+    /// Position of `value` in the axis range, from 0.0 (minimum) to 1.0 (maximum).
+    fn position(&self, axis: AbsoluteAxisCode, value: i32) -> Option<f32> {
+        let (min, max) = *self.ranges.get(&axis)?;
+        let span = i64::from(max) - i64::from(min);
+        if span <= 0 {
+            return None;
+        }
+        Some(((i64::from(value) - i64::from(min)) as f32 / span as f32).clamp(0.0, 1.0))
+    }
+}
+
+/// Hysteresis: a pressed axis stays pressed until it moves back past the release point.
+fn is_engaged(trigger: &AxisTrigger, position: f32, was_engaged: bool) -> bool {
+    // Pressed towards the maximum unless the release point lies above the press point.
+    let towards_max = trigger.press >= trigger.release;
+    if was_engaged {
+        if towards_max {
+            position > trigger.release
+        } else {
+            position < trigger.release
+        }
+    } else if towards_max {
+        position >= trigger.press
+    } else {
+        position <= trigger.press
+    }
+}
+
+pub(crate) fn supports_axis(device: &Device, axis: AbsoluteAxisCode) -> bool {
+    device
+        .supported_absolute_axes()
+        .is_some_and(|axes| axes.contains(axis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{config_from_persisted, PersistedConfig};
+
+    fn trigger(press: f32, release: f32) -> AxisTrigger {
+        AxisTrigger {
+            axis: AbsoluteAxisCode::ABS_Z,
+            press,
+            release,
+        }
+    }
+
+    #[test]
+    fn presses_towards_maximum_with_hysteresis() {
+        let pedal = trigger(0.6, 0.4);
+        assert!(!is_engaged(&pedal, 0.59, false));
+        assert!(is_engaged(&pedal, 0.6, false));
+        // Between the release and press points the previous state holds.
+        assert!(is_engaged(&pedal, 0.5, true));
+        assert!(!is_engaged(&pedal, 0.5, false));
+        assert!(!is_engaged(&pedal, 0.4, true));
+        assert!(!is_engaged(&pedal, 0.0, true));
+    }
+
+    #[test]
+    fn presses_towards_minimum_with_hysteresis() {
+        let pedal = trigger(0.3, 0.5);
+        assert!(!is_engaged(&pedal, 0.31, false));
+        assert!(is_engaged(&pedal, 0.3, false));
+        assert!(is_engaged(&pedal, 0.4, true));
+        assert!(!is_engaged(&pedal, 0.4, false));
+        assert!(!is_engaged(&pedal, 0.5, true));
+        assert!(!is_engaged(&pedal, 1.0, true));
+    }
+
+    #[test]
+    fn equal_points_press_towards_maximum() {
+        let pedal = trigger(0.5, 0.5);
+        assert!(is_engaged(&pedal, 0.5, false));
+        assert!(!is_engaged(&pedal, 0.5, true));
+    }
+
+    #[test]
+    fn maps_values_into_axis_range() {
+        let state = AxisState {
+            ranges: HashMap::from([
+                (AbsoluteAxisCode::ABS_Z, (0, 255)),
+                (AbsoluteAxisCode::ABS_X, (-32768, 32767)),
+                (AbsoluteAxisCode::ABS_Y, (5, 5)),
+            ]),
+            engaged: HashSet::new(),
+        };
+        assert_eq!(state.position(AbsoluteAxisCode::ABS_Z, 0), Some(0.0));
+        assert_eq!(state.position(AbsoluteAxisCode::ABS_Z, 255), Some(1.0));
+        assert_eq!(state.position(AbsoluteAxisCode::ABS_Z, 300), Some(1.0));
+        assert_eq!(state.position(AbsoluteAxisCode::ABS_X, -32768), Some(0.0));
+        assert_eq!(state.position(AbsoluteAxisCode::ABS_X, 32767), Some(1.0));
+        // Empty ranges and axes the device does not have give no position.
+        assert_eq!(state.position(AbsoluteAxisCode::ABS_Y, 5), None);
+        assert_eq!(state.position(AbsoluteAxisCode::ABS_RZ, 5), None);
+    }
+
+    #[test]
+    fn update_reports_press_and_release_once() {
+        let mut config = config_from_persisted(PersistedConfig::default()).unwrap();
+        config.bindings[0].keys.clear();
+        config.bindings[0].axis = Some(trigger(0.6, 0.4));
+        let mut state = AxisState {
+            ranges: HashMap::from([(AbsoluteAxisCode::ABS_Z, (0, 100))]),
+            engaged: HashSet::new(),
+        };
+        assert!(!state.update(&config, AbsoluteAxisCode::ABS_Z, 50));
+        assert!(state.update(&config, AbsoluteAxisCode::ABS_Z, 70));
+        assert!(state.is_engaged(0));
+        assert!(!state.update(&config, AbsoluteAxisCode::ABS_Z, 50));
+        assert!(state.is_engaged(0));
+        assert!(state.update(&config, AbsoluteAxisCode::ABS_Z, 30));
+        assert!(!state.is_engaged(0));
+        // Other axes of the device do not touch the binding.
+        assert!(!state.update(&config, AbsoluteAxisCode::ABS_X, 100));
+    }
+}
@@ -0,0 +1,382 @@
+//! Mic control backends selected by the `backend` config key.
+
+mod alsa;
+mod pactl;
+#[cfg(feature = "pipewire")]
+mod pipewire;
+mod wpctl;
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::audio::find_bin;
+use crate::config::{Backend, Config};
+
+/// A capture source as reported by a backend.
+#[derive(Clone, Debug)]
+pub(crate) struct SourceInfo {
+    /// Backend-specific identifier used to address the source.
+    pub(crate) id: String,
+    /// Stable source name (e.g. `alsa_input.usb-...`).
+    pub(crate) name: String,
+    /// Human readable description.
+    pub(crate) description: String,
+}
+
+/// Which source a backend call acts on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Target {
+    /// The system default source.
+    Default,
+    /// A specific source, by its backend identifier.
+    Source(String),
+}
+
+/// Current mute and volume state of a source as read back from the backend.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct MicState {
+    pub(crate) muted: bool,
+    /// Volume on the same scale as `on_level`/`off_level` (1.0 = 100%).
+    pub(crate) volume: f32,
+}
+
+/// The state a source should be in after a transition.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Desired {
+    Volume(f32),
+    Muted(bool),
+}
+
+impl Desired {
+    /// Whether the read-back state satisfies this target state.
+    pub(crate) fn matches(self, state: MicState) -> bool {
+        match self {
+            Desired::Volume(level) => (state.volume - level).abs() <= VOLUME_TOLERANCE,
+            Desired::Muted(muted) => state.muted == muted,
+        }
+    }
+}
+
+/// A way of changing the capture source's volume and mute state.
+pub(crate) trait MicBackend: Send {
+    /// Short name used in log output.
+    fn name(&self) -> &'static str;
+    /// List the capture sources this backend can control.
+    fn list_sources(&mut self) -> Result<Vec<SourceInfo>>;
+    /// Set the capture volume to an absolute level (1.0 = 100%).
+    fn set_volume(&mut self, target: &Target, level: f32) -> Result<()>;
+    /// Mute or unmute the capture source.
+    fn set_mute(&mut self, target: &Target, muted: bool) -> Result<()>;
+    /// Read the current mute and volume state of the capture source.
+    fn get_state(&mut self, target: &Target) -> Result<MicState>;
+}
+
+/// Volume difference still treated as equal (backends round to 1% or mixer steps).
+const VOLUME_TOLERANCE: f32 = 0.02;
+const APPLY_ATTEMPTS: u32 = 3;
+const APPLY_BACKOFF: Duration = Duration::from_millis(25);
+/// How often sources that are missing are looked up again.
+pub(crate) const SOURCE_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Run a helper tool and fail if it exits unsuccessfully.
+pub(super) fn run_tool(command: &mut Command, tool: &str) -> Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to run {tool}"))?;
+    if !status.success() {
+        bail!("{tool} exited with {status}");
+    }
+    Ok(())
+}
+
+/// Run a helper tool and return its stdout, failing on a non-zero exit.
+pub(super) fn tool_output(command: &mut Command, tool: &str) -> Result<String> {
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {tool}"))?;
+    if !output.status.success() {
+        bail!(
+            "{tool} exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+struct ActiveBackend {
+    backend: Box<dyn MicBackend>,
+    /// Configured `source` of each target in `Config::targets` order.
+    sources: Vec<Option<String>>,
+    /// Resolved targets in the same order (`None` = not found yet).
+    targets: Vec<Option<Target>>,
+}
+
+impl ActiveBackend {
+    fn new(backend: Box<dyn MicBackend>, config: &Config) -> Self {
+        let mut active = Self {
+            backend,
+            sources: config.targets.iter().map(|t| t.source.clone()).collect(),
+            targets: Vec::new(),
+        };
+        active.resolve_all();
+        active
+    }
+
+    /// Resolve every configured target; unresolved ones are reported and left pending.
+    fn resolve_all(&mut self) {
+        self.targets = vec![None; self.sources.len()];
+        for index in 0..self.sources.len() {
+            if let Err(err) = self.resolve(index) {
+                eprintln!("{err:#}");
+                println!("Waiting for capture source...");
+            }
+        }
+    }
+
+    /// Look up the `index`-th configured source again and remember the result.
+    fn resolve(&mut self, index: usize) -> Result<Target> {
+        let source = self
+            .sources
+            .get(index)
+            .context("No such capture source")?
+            .clone();
+        let resolved = match source.as_deref() {
+            None => Ok(Target::Default),
+            Some(spec) => find_source(self.backend.as_mut(), spec).map(|found| {
+                let target = Target::Source(found.id.clone());
+                if self.targets[index].as_ref() != Some(&target) {
+                    println!("Mic source: {} - {}", found.id, found.name);
+                }
+                target
+            }),
+        };
+        self.targets[index] = resolved.as_ref().ok().cloned();
+        resolved
+    }
+}
+
+static BACKEND: OnceLock<Mutex<ActiveBackend>> = OnceLock::new();
+
+fn pipewire_running() -> bool {
+    env::var("XDG_RUNTIME_DIR")
+        .map(|dir| PathBuf::from(dir).join("pipewire-0").exists())
+        .unwrap_or(false)
+}
+
+/// Pick a backend when none is configured: PipeWire, then PulseAudio, then ALSA.
+fn detect_backend() -> Backend {
+    // The native backend connects on its own and falls back to wpctl while it cannot.
+    #[cfg(feature = "pipewire")]
+    if pipewire_running() {
+        return Backend::Pipewire;
+    }
+    if pipewire_running() && find_bin("wpctl").is_some() {
+        return Backend::Wpctl;
+    }
+    if find_bin("pactl").is_some() {
+        return Backend::Pactl;
+    }
+    Backend::Alsa
+}
+
+fn create_backend(config: &Config) -> Result<Box<dyn MicBackend>> {
+    let backend = match config.backend {
+        Backend::Auto => detect_backend(),
+        other => other,
+    };
+    match backend {
+        Backend::Auto => unreachable!("auto backend is resolved above"),
+        #[cfg(feature = "pipewire")]
+        Backend::Pipewire => Ok(Box::new(pipewire::PipewireBackend::new())),
+        #[cfg(not(feature = "pipewire"))]
+        Backend::Pipewire => {
+            bail!("pttkey was built without native PipeWire support; rebuild with --features pipewire or use --backend wpctl")
+        }
+        Backend::Wpctl => Ok(Box::new(wpctl::WpctlBackend)),
+        Backend::Pactl => Ok(Box::new(pactl::PactlBackend)),
+        Backend::Alsa => Ok(Box::new(alsa::AlsaBackend::new(
+            &config.alsa_card,
+            &config.alsa_control,
+        )?)),
+    }
+}
+
+/// Find the source matching a configured id, name or description.
+fn find_source(backend: &mut dyn MicBackend, spec: &str) -> Result<SourceInfo> {
+    let sources = backend.list_sources()?;
+    let found = sources
+        .iter()
+        .find(|s| s.id == spec)
+        .or_else(|| sources.iter().find(|s| s.name == spec))
+        .or_else(|| {
+            sources
+                .iter()
+                .find(|s| s.description.eq_ignore_ascii_case(spec))
+        })
+        .with_context(|| {
+            format!(
+                "No capture source matches '{spec}' (backend {}). Use --list-sources.",
+                backend.name()
+            )
+        })?;
+    Ok(found.clone())
+}
+
+/// Create (or replace) the active mic backend from the config.
+pub(crate) fn init_backend(config: &Config) -> Result<()> {
+    let backend = create_backend(config)?;
+    println!("Mic backend: {}", backend.name());
+    let active = ActiveBackend::new(backend, config);
+    if let Some(cell) = BACKEND.get() {
+        if let Ok(mut guard) = cell.lock() {
+            *guard = active;
+        }
+        return Ok(());
+    }
+    let _ = BACKEND.set(Mutex::new(active));
+    Ok(())
+}
+
+/// Resolve the configured sources again, e.g. after a config reload.
+pub(crate) fn refresh_sources(config: &Config) -> Result<()> {
+    with_backend(|active| {
+        active.sources = config.targets.iter().map(|t| t.source.clone()).collect();
+        active.resolve_all();
+        Ok(())
+    })
+}
+
+/// Whether a configured source has not been found yet.
+pub(crate) fn has_pending_sources() -> bool {
+    with_backend(|active| Ok(active.targets.iter().any(Option::is_none))).unwrap_or(false)
+}
+
+/// Whether the `index`-th configured source has not been found yet.
+pub(crate) fn is_pending(index: usize) -> bool {
+    with_backend(|active| Ok(active.targets.get(index).is_some_and(Option::is_none)))
+        .unwrap_or(false)
+}
+
+/// Look up the sources that were missing; returns the indexes of those found now.
+pub(crate) fn resolve_pending_sources() -> Vec<usize> {
+    with_backend(|active| {
+        let pending: Vec<usize> = (0..active.targets.len())
+            .filter(|&index| active.targets[index].is_none())
+            .collect();
+        Ok(pending
+            .into_iter()
+            .filter(|&index| active.resolve(index).is_ok())
+            .collect())
+    })
+    .unwrap_or_default()
+}
+
+fn with_backend<T>(f: impl FnOnce(&mut ActiveBackend) -> Result<T>) -> Result<T> {
+    let cell = BACKEND
+        .get()
+        .ok_or_else(|| anyhow!("Mic backend not initialized"))?;
+    let mut guard = cell
+        .lock()
+        .map_err(|_| anyhow!("Mic backend lock poisoned"))?;
+    f(&mut guard)
+}
+
+/// Run `f` on the `index`-th target. When it fails the source is looked up again, since a
+/// replugged device comes back under a new node id; a source that is gone becomes pending.
+fn with_target<T>(
+    index: usize,
+    f: impl Fn(&mut dyn MicBackend, &Target) -> Result<T>,
+) -> Result<T> {
+    with_backend(|active| {
+        let target = active
+            .targets
+            .get(index)
+            .cloned()
+            .flatten()
+            .context("Capture source is not available")?;
+        let err = match f(active.backend.as_mut(), &target) {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        match active.resolve(index) {
+            Ok(resolved) if resolved != target => f(active.backend.as_mut(), &resolved),
+            _ => Err(err),
+        }
+    })
+}
+
+fn set_desired(backend: &mut dyn MicBackend, target: &Target, desired: Desired) -> Result<()> {
+    match desired {
+        Desired::Volume(level) => backend.set_volume(target, level),
+        Desired::Muted(muted) => backend.set_mute(target, muted),
+    }
+}
+
+/// Whether the source is already in the desired state (`false` if unreadable).
+fn in_desired_state(backend: &mut dyn MicBackend, target: &Target, desired: Desired) -> bool {
+    backend
+        .get_state(target)
+        .map(|state| desired.matches(state))
+        .unwrap_or(false)
+}
+
+/// Move the `index`-th configured target to `desired`, retrying with backoff
+/// until the backend reports the new state.
+pub(crate) fn apply(index: usize, desired: Desired) -> Result<()> {
+    with_target(index, |backend, target| {
+        let mut backoff = APPLY_BACKOFF;
+        let mut last_error = None;
+        for attempt in 0..APPLY_ATTEMPTS {
+            if attempt > 0 {
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                // Some backends apply asynchronously; the change may have landed meanwhile.
+                if last_error.is_none() && in_desired_state(backend, target, desired) {
+                    return Ok(());
+                }
+            }
+            if let Err(err) = set_desired(backend, target, desired) {
+                last_error = Some(err);
+                continue;
+            }
+            last_error = None;
+            match backend.get_state(target) {
+                Ok(state) if !desired.matches(state) => {}
+                // Nothing to verify against; trust the successful call.
+                _ => return Ok(()),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            anyhow!("backend did not reach the requested state after {APPLY_ATTEMPTS} attempts")
+        }))
+    })
+}
+
+/// Read the current state of the `index`-th configured target.
+pub(crate) fn read_state(index: usize) -> Result<MicState> {
+    with_target(index, |backend, target| backend.get_state(target))
+}
+
+/// Verify the backend settings and sources without touching the mic.
+pub(crate) fn check_backend(config: &Config) -> Result<&'static str> {
+    let mut backend = create_backend(config)?;
+    for source in config.targets.iter().filter_map(|t| t.source.as_deref()) {
+        let found = find_source(backend.as_mut(), source)?;
+        println!("Mic source: {} - {}", found.id, found.name);
+    }
+    Ok(backend.name())
+}
+
+/// Print the capture sources of the configured backend.
+pub(crate) fn print_sources(config: &Config) -> Result<()> {
+    let mut backend = create_backend(config)?;
+    for source in backend.list_sources()? {
+        println!("{} - {} ({})", source.id, source.name, source.description);
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! ALSA mixer control for systems without a sound server.
+
+use ::alsa::card;
+use ::alsa::mixer::{Mixer, Selem, SelemChannelId, SelemId};
+use anyhow::{bail, Context, Result};
+
+use super::{MicBackend, MicState, SourceInfo, Target};
+
+/// Capture volume/switch on a simple mixer control (e.g. `Capture` on `hw:0`).
+pub(crate) struct AlsaBackend {
+    card: String,
+    control: String,
+}
+
+impl AlsaBackend {
+    pub(crate) fn new(card: &str, control: &str) -> Result<Self> {
+        let backend = Self {
+            card: card.to_string(),
+            control: control.to_string(),
+        };
+        // Fail early on a typo instead of on the first key press.
+        backend.with_control(&Target::Default, |_| Ok(()))?;
+        Ok(backend)
+    }
+
+    /// Run `f` on the capture control of the target card (`Default` = configured card).
+    fn with_control(&self, target: &Target, f: impl FnOnce(&Selem) -> Result<()>) -> Result<()> {
+        let card = match target {
+            Target::Default => self.card.as_str(),
+            Target::Source(card) => card.as_str(),
+        };
+        let mixer =
+            Mixer::new(card, false).with_context(|| format!("Failed to open ALSA mixer {card}"))?;
+        let selem = mixer
+            .find_selem(&SelemId::new(&self.control, 0))
+            .with_context(|| format!("ALSA control '{}' not found on {card}", self.control))?;
+        if !selem.has_capture_volume() && !selem.has_capture_switch() {
+            bail!(
+                "ALSA control '{}' on {card} has no capture volume or switch",
+                self.control
+            );
+        }
+        f(&selem)
+    }
+}
+
+impl MicBackend for AlsaBackend {
+    fn name(&self) -> &'static str {
+        "alsa"
+    }
+
+    fn list_sources(&mut self) -> Result<Vec<SourceInfo>> {
+        let mut sources = Vec::new();
+        for card in card::Iter::new() {
+            let card = card.context("Failed to enumerate ALSA cards")?;
+            let id = format!("hw:{}", card.get_index());
+            // Only cards that expose the configured capture control are candidates.
+            if self
+                .with_control(&Target::Source(id.clone()), |_| Ok(()))
+                .is_err()
+            {
+                continue;
+            }
+            let name = card.get_name().unwrap_or_else(|_| id.clone());
+            let description = card.get_longname().unwrap_or_else(|_| name.clone());
+            sources.push(SourceInfo {
+                id,
+                name,
+                description,
+            });
+        }
+        Ok(sources)
+    }
+
+    fn set_volume(&mut self, target: &Target, level: f32) -> Result<()> {
+        self.with_control(target, |selem| {
+            if !selem.has_capture_volume() {
+                bail!("ALSA control '{}' has no capture volume", self.control);
+            }
+            let (min, max) = selem.get_capture_volume_range();
+            let value = min + ((max - min) as f32 * level.clamp(0.0, 1.0)).round() as i64;
+            selem
+                .set_capture_volume_all(value)
+                .context("Failed to set ALSA capture volume")
+        })
+    }
+
+    fn set_mute(&mut self, target: &Target, muted: bool) -> Result<()> {
+        self.with_control(target, |selem| {
+            if !selem.has_capture_switch() {
+                bail!("ALSA control '{}' has no capture switch", self.control);
+            }
+            selem
+                .set_capture_switch_all(if muted { 0 } else { 1 })
+                .context("Failed to set ALSA capture switch")
+        })
+    }
+
+    fn get_state(&mut self, target: &Target) -> Result<MicState> {
+        let mut state = MicState {
+            muted: false,
+            volume: 1.0,
+        };
+        self.with_control(target, |selem| {
+            let channel = SelemChannelId::mono();
+            if selem.has_capture_switch() {
+                state.muted = selem
+                    .get_capture_switch(channel)
+                    .context("Failed to read ALSA capture switch")?
+                    == 0;
+            }
+            if selem.has_capture_volume() {
+                let (min, max) = selem.get_capture_volume_range();
+                let value = selem
+                    .get_capture_volume(channel)
+                    .context("Failed to read ALSA capture volume")?;
+                if max > min {
+                    state.volume = (value - min) as f32 / (max - min) as f32;
+                }
+            }
+            Ok(())
+        })?;
+        Ok(state)
+    }
+}
@@ -0,0 +1,174 @@
+//! PulseAudio (or pipewire-pulse) control by spawning `pactl`.
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::process::Command;
+
+use super::{run_tool, tool_output, MicBackend, MicState, SourceInfo, Target};
+use crate::audio::find_bin;
+
+pub(crate) struct PactlBackend;
+
+fn pactl() -> Result<PathBuf> {
+    find_bin("pactl").context("pactl not found in PATH or /usr/bin")
+}
+
+fn target_arg(target: &Target) -> &str {
+    match target {
+        Target::Default => "@DEFAULT_SOURCE@",
+        Target::Source(name) => name,
+    }
+}
+
+/// Parse `pactl list sources`, skipping sink monitors.
+fn parse_sources(output: &str) -> Vec<SourceInfo> {
+    let mut sources = Vec::new();
+    let mut name: Option<String> = None;
+    let mut description: Option<String> = None;
+    let mut flush = |name: &mut Option<String>, description: &mut Option<String>| {
+        if let Some(name) = name.take() {
+            let description = description.take().unwrap_or_else(|| name.clone());
+            if !name.ends_with(".monitor") {
+                sources.push(SourceInfo {
+                    id: name.clone(),
+                    name,
+                    description,
+                });
+            }
+        }
+    };
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("Source #") {
+            flush(&mut name, &mut description);
+        } else if let Some(value) = line.strip_prefix("Name: ") {
+            name = Some(value.to_string());
+        } else if let Some(value) = line.strip_prefix("Description: ") {
+            description = Some(value.to_string());
+        }
+    }
+    flush(&mut name, &mut description);
+    sources
+}
+
+/// Take the first channel percentage from `pactl get-source-volume` output,
+/// e.g. `Volume: front-left: 26214 /  40% / -23.88 dB, ...`.
+fn parse_volume_percent(output: &str) -> Option<f32> {
+    let percent = output
+        .split('/')
+        .map(str::trim)
+        .find_map(|part| part.strip_suffix('%'))?;
+    Some(percent.trim().parse::<f32>().ok()? / 100.0)
+}
+
+impl MicBackend for PactlBackend {
+    fn name(&self) -> &'static str {
+        "pactl"
+    }
+
+    fn list_sources(&mut self) -> Result<Vec<SourceInfo>> {
+        let output = tool_output(
+            Command::new(pactl()?)
+                .args(["list", "sources"])
+                .env("LC_ALL", "C"),
+            "pactl",
+        )?;
+        Ok(parse_sources(&output))
+    }
+
+    fn set_volume(&mut self, target: &Target, level: f32) -> Result<()> {
+        let percent = (level.max(0.0) * 100.0).round() as u32;
+        run_tool(
+            Command::new(pactl()?).args([
+                "set-source-volume",
+                target_arg(target),
+                &format!("{percent}%"),
+            ]),
+            "pactl",
+        )
+    }
+
+    fn set_mute(&mut self, target: &Target, muted: bool) -> Result<()> {
+        run_tool(
+            Command::new(pactl()?).args([
+                "set-source-mute",
+                target_arg(target),
+                if muted { "1" } else { "0" },
+            ]),
+            "pactl",
+        )
+    }
+
+    fn get_state(&mut self, target: &Target) -> Result<MicState> {
+        let pactl = pactl()?;
+        let mute = tool_output(
+            Command::new(&pactl)
+                .args(["get-source-mute", target_arg(target)])
+                .env("LC_ALL", "C"),
+            "pactl",
+        )?;
+        let volume = tool_output(
+            Command::new(&pactl)
+                .args(["get-source-volume", target_arg(target)])
+                .env("LC_ALL", "C"),
+            "pactl",
+        )?;
+        Ok(MicState {
+            muted: mute.trim() == "Mute: yes",
+            volume: parse_volume_percent(&volume)
+                .with_context(|| format!("Unexpected pactl output '{}'", volume.trim()))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_sources_without_monitors() {
+        let output = "\
+Source #0
+\tState: SUSPENDED
+\tName: alsa_output.pci-0000_00_1f.3.analog-stereo.monitor
+\tDescription: Monitor of Built-in Audio Analog Stereo
+Source #1
+\tState: RUNNING
+\tName: alsa_input.usb-046d_HD_Webcam_C615-02.analog-stereo
+\tDescription: HD Webcam C615 Analog Stereo
+\tProperties:
+\t\tdevice.description = \"HD Webcam C615\"
+Source #2
+\tName: virtual_mic
+";
+        let sources = parse_sources(output);
+        assert_eq!(sources.len(), 2);
+        assert_eq!(
+            sources[0].id,
+            "alsa_input.usb-046d_HD_Webcam_C615-02.analog-stereo"
+        );
+        assert_eq!(sources[0].name, sources[0].id);
+        assert_eq!(sources[0].description, "HD Webcam C615 Analog Stereo");
+        // The description falls back to the name.
+        assert_eq!(sources[1].name, "virtual_mic");
+        assert_eq!(sources[1].description, "virtual_mic");
+    }
+
+    #[test]
+    fn takes_first_channel_percent() {
+        let output = "Volume: front-left: 26214 /  40% / -23.88 dB,   front-right: 32768 /  50% / -18.06 dB\n        balance 0.00\n";
+        assert_eq!(parse_volume_percent(output), Some(0.40));
+    }
+
+    #[test]
+    fn parses_mono_volume_above_100_percent() {
+        let output = "Volume: mono: 98304 / 150% / 10.57 dB\n";
+        assert_eq!(parse_volume_percent(output), Some(1.5));
+    }
+
+    #[test]
+    fn rejects_output_without_percent() {
+        assert_eq!(parse_volume_percent(""), None);
+        assert_eq!(parse_volume_percent("No such entity"), None);
+    }
+}
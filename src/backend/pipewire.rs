@@ -0,0 +1,547 @@
+//! In-process PipeWire control for capture sources.
+//!
+//! A dedicated thread keeps one connection to the PipeWire daemon, tracks the
+//! `Audio/Source` nodes and the default source from the `default` metadata, and
+//! applies mute/volume changes by setting the node `Props` param directly. Connecting
+//! happens on that thread too, so calls go to `wpctl` until the connection is up instead
+//! of waiting for it. When the connection is lost (e.g. PipeWire restarted), the backend
+//! connects again.
+
+use ::pipewire as pw;
+use anyhow::{anyhow, bail, Context, Result};
+use pw::metadata::{Metadata, MetadataListener};
+use pw::node::{Node, NodeListener};
+use pw::registry::{GlobalObject, Registry};
+use pw::spa::param::ParamType;
+use pw::spa::pod::deserialize::PodDeserializer;
+use pw::spa::pod::serialize::PodSerializer;
+use pw::spa::pod::{Object, Pod, Property, Value, ValueArray};
+use pw::spa::utils::SpaTypes;
+use pw::types::ObjectType;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::wpctl::WpctlBackend;
+use super::{MicBackend, MicState, SourceInfo, Target};
+
+/// How long to wait for the first connection at startup, and after how long a pending
+/// attempt is reported as failing.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const REPLY_TIMEOUT: Duration = Duration::from_millis(500);
+/// How often to try connecting again while PipeWire is unreachable.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_SOURCE_KEY: &str = "default.audio.source";
+
+enum Command {
+    ListSources(mpsc::Sender<Result<Vec<SourceInfo>>>),
+    GetState(Target, mpsc::Sender<Result<MicState>>),
+    SetMute(Target, bool, mpsc::Sender<Result<()>>),
+    SetVolume(Target, f32, mpsc::Sender<Result<()>>),
+}
+
+struct NativeControl {
+    sender: pw::channel::Sender<Command>,
+    /// Set once the initial roundtrips are done, cleared when the connection is lost.
+    connected: Arc<AtomicBool>,
+    /// Outcome of the connection attempt; disconnected once the thread has exited.
+    ready: mpsc::Receiver<Result<()>>,
+    started: Instant,
+}
+
+struct SourceNode {
+    global: GlobalObject<pw::properties::Properties>,
+    name: Option<String>,
+    description: Option<String>,
+    proxy: Option<(Node, NodeListener)>,
+    channels: usize,
+    /// Last `Props` reported by the node, used to read back the mic state.
+    state: Option<MicState>,
+}
+
+#[derive(Default)]
+struct State {
+    sources: HashMap<u32, SourceNode>,
+    default_source: Option<String>,
+    metadata: Option<(Metadata, MetadataListener)>,
+}
+
+/// Start connecting on a new thread without waiting for it.
+fn connect() -> Result<NativeControl> {
+    let (sender, receiver) = pw::channel::channel::<Command>();
+    let (ready_tx, ready_rx) = mpsc::channel::<Result<()>>();
+    let connected = Arc::new(AtomicBool::new(false));
+    let thread_connected = connected.clone();
+    std::thread::Builder::new()
+        .name("pttkey-pipewire".to_string())
+        .spawn(move || {
+            let result = run_loop(receiver, ready_tx.clone(), thread_connected.clone());
+            if thread_connected.swap(false, Ordering::SeqCst) {
+                eprintln!("Lost connection to PipeWire, falling back to wpctl");
+            } else {
+                let err = result
+                    .err()
+                    .unwrap_or_else(|| anyhow!("PipeWire closed the connection"));
+                let _ = ready_tx.send(Err(err));
+            }
+        })
+        .context("Failed to spawn PipeWire thread")?;
+
+    Ok(NativeControl {
+        sender,
+        connected,
+        ready: ready_rx,
+        started: Instant::now(),
+    })
+}
+
+fn run_loop(
+    receiver: pw::channel::Receiver<Command>,
+    ready: mpsc::Sender<Result<()>>,
+    connected: Arc<AtomicBool>,
+) -> Result<()> {
+    pw::init();
+    let mainloop = pw::main_loop::MainLoop::new(None).context("Failed to create PipeWire loop")?;
+    let context =
+        pw::context::Context::new(&mainloop).context("Failed to create PipeWire context")?;
+    let core = context
+        .connect(None)
+        .context("Failed to connect to PipeWire")?;
+    let registry = Rc::new(
+        core.get_registry()
+            .context("Failed to get PipeWire registry")?,
+    );
+    let state = Rc::new(RefCell::new(State::default()));
+
+    let _registry_listener = {
+        let state = state.clone();
+        let state_remove = state.clone();
+        let registry_bind = registry.clone();
+        registry
+            .add_listener_local()
+            .global(move |global| on_global(&registry_bind, &state, global))
+            .global_remove(move |id| {
+                state_remove.borrow_mut().sources.remove(&id);
+            })
+            .register()
+    };
+
+    // Two roundtrips: the first collects globals, the second the params of the
+    // source nodes bound while handling them.
+    let pending = Rc::new(Cell::new(Some(
+        core.sync(0).context("PipeWire sync failed")?,
+    )));
+    let roundtrips_left = Rc::new(Cell::new(2u8));
+    let _core_listener = {
+        let mainloop = mainloop.clone();
+        let core_sync = core.clone();
+        let connected = connected.clone();
+        core.add_listener_local()
+            .done(move |id, seq| {
+                if id != pw::core::PW_ID_CORE || pending.get() != Some(seq) {
+                    return;
+                }
+                pending.set(None);
+                roundtrips_left.set(roundtrips_left.get().saturating_sub(1));
+                if roundtrips_left.get() > 0 {
+                    if let Ok(next) = core_sync.sync(0) {
+                        pending.set(Some(next));
+                        return;
+                    }
+                }
+                connected.store(true, Ordering::SeqCst);
+                let _ = ready.send(Ok(()));
+            })
+            .error(move |id, _seq, res, message| {
+                eprintln!("PipeWire error on object {id}: {message} ({res})");
+                if id == pw::core::PW_ID_CORE {
+                    mainloop.quit();
+                }
+            })
+            .register()
+    };
+
+    let _receiver = {
+        let state = state.clone();
+        receiver.attach(mainloop.loop_(), move |command| match command {
+            Command::ListSources(reply) => {
+                let _ = reply.send(Ok(list_sources(&state)));
+            }
+            Command::GetState(target, reply) => {
+                let _ = reply.send(get_state(&state, &target));
+            }
+            Command::SetMute(target, muted, reply) => {
+                let _ = reply.send(set_props(&state, &target, Props::Mute(muted)));
+            }
+            Command::SetVolume(target, level, reply) => {
+                let _ = reply.send(set_props(&state, &target, Props::Volume(level)));
+            }
+        })
+    };
+
+    mainloop.run();
+    Ok(())
+}
+
+fn on_global(
+    registry: &Rc<Registry>,
+    state: &Rc<RefCell<State>>,
+    global: &GlobalObject<&pw::spa::utils::dict::DictRef>,
+) {
+    let Some(props) = global.props else {
+        return;
+    };
+    match global.type_ {
+        ObjectType::Node if props.get("media.class") == Some("Audio/Source") => {
+            let name = props.get("node.name").map(str::to_string);
+            let description = props.get("node.description").map(str::to_string);
+            let proxy = bind_node(registry, state, global);
+            state.borrow_mut().sources.insert(
+                global.id,
+                SourceNode {
+                    global: global.to_owned(),
+                    name,
+                    description,
+                    proxy,
+                    channels: 0,
+                    state: None,
+                },
+            );
+        }
+        ObjectType::Metadata if props.get("metadata.name") == Some("default") => {
+            let Ok(metadata) = registry.bind::<Metadata, _>(global) else {
+                return;
+            };
+            let state_meta = state.clone();
+            let listener = metadata
+                .add_listener_local()
+                .property(move |subject, key, _type, value| {
+                    if subject == pw::core::PW_ID_CORE
+                        && matches!(key, Some(DEFAULT_SOURCE_KEY) | None)
+                    {
+                        state_meta.borrow_mut().default_source = value.and_then(parse_default_name);
+                    }
+                    0
+                })
+                .register();
+            state.borrow_mut().metadata = Some((metadata, listener));
+        }
+        _ => {}
+    }
+}
+
+fn bind_node(
+    registry: &Registry,
+    state: &Rc<RefCell<State>>,
+    global: &GlobalObject<&pw::spa::utils::dict::DictRef>,
+) -> Option<(Node, NodeListener)> {
+    let node: Node = registry.bind(global).ok()?;
+    let id = global.id;
+    let state = state.clone();
+    let listener = node
+        .add_listener_local()
+        .param(move |_seq, param_type, _index, _next, pod| {
+            if param_type != ParamType::Props {
+                return;
+            }
+            let Some((channels, mic_state)) = pod.and_then(parse_props) else {
+                return;
+            };
+            if let Some(source) = state.borrow_mut().sources.get_mut(&id) {
+                source.channels = channels;
+                source.state = Some(mic_state);
+            }
+        })
+        .register();
+    node.subscribe_params(&[ParamType::Props]);
+    Some((node, listener))
+}
+
+/// Extract the node name from a metadata value like `{ "name": "alsa_input..." }`.
+fn parse_default_name(value: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(value).ok()?;
+    Some(value.get("name")?.as_str()?.to_string())
+}
+
+/// Read the channel count, mute flag and volume from a `Props` param.
+fn parse_props(pod: &Pod) -> Option<(usize, MicState)> {
+    let (_, value) = PodDeserializer::deserialize_any_from(pod.as_bytes()).ok()?;
+    let Value::Object(object) = value else {
+        return None;
+    };
+    let mut volumes = None;
+    let mut muted = false;
+    for prop in &object.properties {
+        match &prop.value {
+            Value::ValueArray(ValueArray::Float(values))
+                if prop.key == pw::spa::sys::SPA_PROP_channelVolumes =>
+            {
+                volumes = Some(values.clone());
+            }
+            Value::Bool(value) if prop.key == pw::spa::sys::SPA_PROP_mute => muted = *value,
+            _ => {}
+        }
+    }
+    let volumes = volumes?;
+    let volume = volumes.first().copied().unwrap_or(0.0).max(0.0).cbrt();
+    Some((volumes.len(), MicState { muted, volume }))
+}
+
+enum Props {
+    Mute(bool),
+    Volume(f32),
+}
+
+fn list_sources(state: &Rc<RefCell<State>>) -> Vec<SourceInfo> {
+    let state = state.borrow();
+    let mut sources: Vec<SourceInfo> = state
+        .sources
+        .iter()
+        .filter_map(|(id, source)| {
+            let name = source.name.clone()?;
+            Some(SourceInfo {
+                id: id.to_string(),
+                description: source.description.clone().unwrap_or_else(|| name.clone()),
+                name,
+            })
+        })
+        .collect();
+    sources.sort_by_key(|source| source.id.parse::<u32>().unwrap_or(u32::MAX));
+    sources
+}
+
+fn find_source<'a>(state: &'a State, target: &Target) -> Result<&'a SourceNode> {
+    match target {
+        Target::Default => {
+            let default_name = state
+                .default_source
+                .as_deref()
+                .context("PipeWire has no default source")?;
+            state
+                .sources
+                .values()
+                .find(|source| source.name.as_deref() == Some(default_name))
+                .with_context(|| format!("Default source {default_name} not found"))
+        }
+        Target::Source(id) => id
+            .parse::<u32>()
+            .ok()
+            .and_then(|id| state.sources.get(&id))
+            .with_context(|| format!("Source {id} not found")),
+    }
+}
+
+fn get_state(state: &Rc<RefCell<State>>, target: &Target) -> Result<MicState> {
+    let state = state.borrow();
+    let source = find_source(&state, target)?;
+    source
+        .state
+        .with_context(|| format!("Source {} has not reported its state", source.global.id))
+}
+
+fn set_props(state: &Rc<RefCell<State>>, target: &Target, props: Props) -> Result<()> {
+    let state = state.borrow();
+    let source = find_source(&state, target)?;
+    let Some((node, _)) = &source.proxy else {
+        bail!("Source node {} is not bound", source.global.id);
+    };
+
+    let property = match props {
+        Props::Mute(muted) => Property::new(pw::spa::sys::SPA_PROP_mute, Value::Bool(muted)),
+        Props::Volume(level) => {
+            if source.channels == 0 {
+                bail!("Channel layout of source {} is unknown", source.global.id);
+            }
+            // Match wpctl, which maps user-facing volume to cubic node volume.
+            let volume = level.max(0.0).powi(3);
+            Property::new(
+                pw::spa::sys::SPA_PROP_channelVolumes,
+                Value::ValueArray(ValueArray::Float(vec![volume; source.channels])),
+            )
+        }
+    };
+    let value = Value::Object(Object {
+        type_: SpaTypes::ObjectParamProps.as_raw(),
+        id: ParamType::Props.as_raw(),
+        properties: vec![property],
+    });
+    let (bytes, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &value)
+        .map_err(|err| anyhow!("Failed to serialize props: {err:?}"))?;
+    let bytes = bytes.into_inner();
+    let pod = Pod::from_bytes(&bytes).context("Failed to build props pod")?;
+    node.set_param(ParamType::Props, 0, pod);
+    Ok(())
+}
+
+/// Native PipeWire backend that falls back to `wpctl` when disconnected.
+pub(crate) struct PipewireBackend {
+    /// Connection to the PipeWire daemon or the attempt in progress; `None` while it is
+    /// unreachable.
+    native: Option<NativeControl>,
+    /// When the last connection attempt was made.
+    last_attempt: Option<Instant>,
+    /// A connection was up before, so the next one is a reconnect.
+    was_connected: bool,
+    /// The failure of the current outage has been reported.
+    warned: bool,
+    fallback: WpctlBackend,
+}
+
+impl PipewireBackend {
+    pub(crate) fn new() -> Self {
+        let mut backend = Self {
+            native: None,
+            last_attempt: None,
+            was_connected: false,
+            warned: false,
+            fallback: WpctlBackend,
+        };
+        backend.start_connect();
+        // Only startup waits for the connection, so the sources are listed natively.
+        backend.check_connection(CONNECT_TIMEOUT);
+        backend
+    }
+
+    fn start_connect(&mut self) {
+        self.last_attempt = Some(Instant::now());
+        match connect() {
+            Ok(control) => self.native = Some(control),
+            Err(err) => self.warn(&err),
+        }
+    }
+
+    fn warn(&mut self, err: &anyhow::Error) {
+        if !self.warned {
+            eprintln!("Native PipeWire control unavailable, using wpctl: {err}");
+            self.warned = true;
+        }
+    }
+
+    /// Take in what the PipeWire thread reported, waiting up to `wait` for it.
+    fn check_connection(&mut self, wait: Duration) {
+        let Some(control) = &self.native else {
+            return;
+        };
+        let outcome = control.ready.recv_timeout(wait);
+        let pending = !control.connected.load(Ordering::SeqCst);
+        let overdue = control.started.elapsed() >= CONNECT_TIMEOUT;
+        match outcome {
+            Ok(Ok(())) => {
+                if self.was_connected {
+                    println!("Reconnected to PipeWire");
+                }
+                self.was_connected = true;
+                self.warned = false;
+            }
+            Ok(Err(err)) => {
+                self.warn(&err);
+                self.native = None;
+            }
+            // Keep waiting instead of starting another attempt next to this one.
+            Err(RecvTimeoutError::Timeout) if pending && overdue => {
+                self.warn(&anyhow!("timed out connecting to PipeWire"));
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // The thread has exited after losing the connection.
+            Err(RecvTimeoutError::Disconnected) => self.native = None,
+        }
+    }
+
+    /// The live connection, without waiting for one; a lost one is replaced, at most every
+    /// `RECONNECT_INTERVAL`.
+    fn native_control(&mut self) -> Option<&NativeControl> {
+        self.check_connection(Duration::ZERO);
+        if self.native.is_none()
+            && self
+                .last_attempt
+                .is_none_or(|at| at.elapsed() >= RECONNECT_INTERVAL)
+        {
+            self.start_connect();
+        }
+        self.native
+            .as_ref()
+            .filter(|control| control.connected.load(Ordering::SeqCst))
+    }
+
+    /// Run a command on the PipeWire thread; `None` if there is no connection.
+    fn send_command<T>(
+        &mut self,
+        build: impl FnOnce(mpsc::Sender<Result<T>>) -> Command,
+    ) -> Option<Result<T>> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        let sent = self.native_control()?.sender.send(build(reply_tx)).is_ok();
+        if !sent {
+            // The PipeWire thread is gone; connect again on a later call.
+            self.native = None;
+            return None;
+        }
+        Some(
+            reply_rx
+                .recv_timeout(REPLY_TIMEOUT)
+                .map_err(|_| anyhow!("PipeWire did not respond"))
+                .and_then(|result| result),
+        )
+    }
+}
+
+impl MicBackend for PipewireBackend {
+    fn name(&self) -> &'static str {
+        "pipewire"
+    }
+
+    fn list_sources(&mut self) -> Result<Vec<SourceInfo>> {
+        match self.send_command(Command::ListSources) {
+            Some(result) => result,
+            None => self.fallback.list_sources(),
+        }
+    }
+
+    fn set_volume(&mut self, target: &Target, level: f32) -> Result<()> {
+        match self.send_command(|reply| Command::SetVolume(target.clone(), level, reply)) {
+            Some(Ok(())) => return Ok(()),
+            Some(Err(err)) => eprintln!("Native PipeWire volume failed, using wpctl: {err}"),
+            None => {}
+        }
+        self.fallback.set_volume(target, level)
+    }
+
+    fn set_mute(&mut self, target: &Target, muted: bool) -> Result<()> {
+        match self.send_command(|reply| Command::SetMute(target.clone(), muted, reply)) {
+            Some(Ok(())) => return Ok(()),
+            Some(Err(err)) => eprintln!("Native PipeWire mute failed, using wpctl: {err}"),
+            None => {}
+        }
+        self.fallback.set_mute(target, muted)
+    }
+
+    fn get_state(&mut self, target: &Target) -> Result<MicState> {
+        match self.send_command(|reply| Command::GetState(target.clone(), reply)) {
+            Some(result) => result,
+            None => self.fallback.get_state(target),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_default_source_name() {
+        assert_eq!(
+            parse_default_name(r#"{ "name": "alsa_input.usb-headset" }"#).as_deref(),
+            Some("alsa_input.usb-headset")
+        );
+        assert_eq!(
+            parse_default_name(r#"{"name":"with \"quotes\""}"#).as_deref(),
+            Some("with \"quotes\"")
+        );
+        assert_eq!(parse_default_name(r#"{ "other": 1 }"#), None);
+        assert_eq!(parse_default_name("alsa_input"), None);
+    }
+}
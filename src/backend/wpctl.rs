@@ -0,0 +1,167 @@
+//! PipeWire control by spawning `wpctl` (and `pw-dump` to list sources).
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::Command;
+
+use super::{run_tool, tool_output, MicBackend, MicState, SourceInfo, Target};
+use crate::audio::find_bin;
+
+pub(crate) struct WpctlBackend;
+
+fn wpctl() -> Result<PathBuf> {
+    find_bin("wpctl").context("wpctl not found in PATH or /usr/bin")
+}
+
+fn target_arg(target: &Target) -> &str {
+    match target {
+        Target::Default => "@DEFAULT_SOURCE@",
+        Target::Source(id) => id,
+    }
+}
+
+/// List `Audio/Source` nodes with `pw-dump`.
+pub(crate) fn pw_dump_sources() -> Result<Vec<SourceInfo>> {
+    let pw_dump = find_bin("pw-dump").context("pw-dump not found in PATH or /usr/bin")?;
+    let output = tool_output(&mut Command::new(pw_dump), "pw-dump")?;
+    parse_pw_dump(&output)
+}
+
+/// Pick the `Audio/Source` nodes out of `pw-dump` output.
+fn parse_pw_dump(output: &str) -> Result<Vec<SourceInfo>> {
+    let objects: Vec<Value> =
+        serde_json::from_str(output).context("Failed to parse pw-dump output")?;
+    let sources = objects
+        .iter()
+        .filter(|obj| obj["type"] == "PipeWire:Interface:Node")
+        .filter_map(|obj| {
+            let props = &obj["info"]["props"];
+            if props["media.class"] != "Audio/Source" {
+                return None;
+            }
+            let name = props["node.name"].as_str()?.to_string();
+            Some(SourceInfo {
+                id: obj["id"].as_u64()?.to_string(),
+                description: props["node.description"]
+                    .as_str()
+                    .unwrap_or(&name)
+                    .to_string(),
+                name,
+            })
+        })
+        .collect();
+    Ok(sources)
+}
+
+/// Parse `wpctl get-volume` output such as `Volume: 0.40 [MUTED]`.
+fn parse_get_volume(output: &str) -> Result<MicState> {
+    let rest = output
+        .trim()
+        .strip_prefix("Volume:")
+        .with_context(|| format!("Unexpected wpctl output '{}'", output.trim()))?;
+    let volume = rest
+        .split_whitespace()
+        .next()
+        .and_then(|value| value.parse::<f32>().ok())
+        .with_context(|| format!("Unexpected wpctl output '{}'", output.trim()))?;
+    Ok(MicState {
+        muted: rest.contains("[MUTED]"),
+        volume,
+    })
+}
+
+impl MicBackend for WpctlBackend {
+    fn name(&self) -> &'static str {
+        "wpctl"
+    }
+
+    fn list_sources(&mut self) -> Result<Vec<SourceInfo>> {
+        pw_dump_sources()
+    }
+
+    fn set_volume(&mut self, target: &Target, level: f32) -> Result<()> {
+        run_tool(
+            Command::new(wpctl()?).args(["set-volume", target_arg(target), &format!("{level}")]),
+            "wpctl",
+        )
+    }
+
+    fn set_mute(&mut self, target: &Target, muted: bool) -> Result<()> {
+        run_tool(
+            Command::new(wpctl()?).args([
+                "set-mute",
+                target_arg(target),
+                if muted { "1" } else { "0" },
+            ]),
+            "wpctl",
+        )
+    }
+
+    fn get_state(&mut self, target: &Target) -> Result<MicState> {
+        let output = tool_output(
+            Command::new(wpctl()?).args(["get-volume", target_arg(target)]),
+            "wpctl",
+        )?;
+        parse_get_volume(&output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_audio_source_nodes() {
+        let output = r#"[
+            {"id": 30, "type": "PipeWire:Interface:Device", "info": {"props": {}}},
+            {"id": 48, "type": "PipeWire:Interface:Node", "info": {"props": {
+                "media.class": "Audio/Sink",
+                "node.name": "alsa_output.pci-0000_00_1f.3.analog-stereo"
+            }}},
+            {"id": 57, "type": "PipeWire:Interface:Node", "info": {"props": {
+                "media.class": "Audio/Source",
+                "node.name": "alsa_input.usb-headset",
+                "node.description": "USB Headset"
+            }}},
+            {"id": 61, "type": "PipeWire:Interface:Node", "info": {"props": {
+                "media.class": "Audio/Source",
+                "node.name": "virtual_mic"
+            }}}
+        ]"#;
+        let sources = parse_pw_dump(output).unwrap();
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].id, "57");
+        assert_eq!(sources[0].name, "alsa_input.usb-headset");
+        assert_eq!(sources[0].description, "USB Headset");
+        // The description falls back to the node name.
+        assert_eq!(sources[1].id, "61");
+        assert_eq!(sources[1].description, "virtual_mic");
+    }
+
+    #[test]
+    fn rejects_invalid_pw_dump_output() {
+        assert!(parse_pw_dump("not json").is_err());
+    }
+
+    #[test]
+    fn parses_unmuted_volume() {
+        let state = parse_get_volume("Volume: 0.40\n").unwrap();
+        assert!(!state.muted);
+        assert!((state.volume - 0.40).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn parses_muted_volume() {
+        let state = parse_get_volume("Volume: 1.00 [MUTED]\n").unwrap();
+        assert!(state.muted);
+        assert!((state.volume - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn rejects_unexpected_output() {
+        assert!(parse_get_volume("").is_err());
+        assert!(parse_get_volume("Object not found").is_err());
+        assert!(parse_get_volume("Volume: loud").is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! Client subcommands (`pttkey status`, `pttkey toggle`, `pttkey set ...`) that talk to the
+//! running daemon over the control socket.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+
+use crate::config::{
+    backup_config_path, config_path, load_persisted_config, parse_args, persisted_from_config,
+    restart_service, set_primary_axis, write_persisted_config,
+};
+use crate::control::{socket_path, Command, Status};
+use crate::watch;
+
+/// Settings that are switched with `--NAME` / `--no-NAME` instead of taking a value.
+const FLAG_SETTINGS: [&str; 4] = ["reverse", "sounds", "suppress", "restore_on_exit"];
+
+/// Settings of the main binding and source; they are changed in place so the extra
+/// `[[bindings]]` and `[[targets]]` stay.
+const PRIMARY_SETTINGS: [&str; 8] = [
+    "keys",
+    "key",
+    "action",
+    "exclusive",
+    "ordered",
+    "axis",
+    "switch",
+    "source",
+];
+
+/// Other settings by config file name (or option name) and the flag that sets them.
+const VALUE_SETTINGS: [(&str, &str); 33] = [
+    ("device_path", "--device"),
+    ("device", "--device"),
+    ("device_name", "--device-name"),
+    ("device_id", "--device-id"),
+    ("device_phys", "--device-phys"),
+    ("device_uniq", "--device-uniq"),
+    ("mode", "--mode"),
+    ("backend", "--backend"),
+    ("alsa_card", "--alsa-card"),
+    ("alsa_control", "--alsa-control"),
+    ("on_level", "--on-level"),
+    ("off_level", "--off-level"),
+    ("sound_on", "--sound-on"),
+    ("sound_off", "--sound-off"),
+    ("sound_volume", "--sound-volume"),
+    ("startup_state", "--startup-state"),
+    ("trigger", "--trigger"),
+    ("toggle_startup_state", "--toggle-startup-state"),
+    ("tap_threshold_ms", "--tap-threshold"),
+    ("press_delay_ms", "--press-delay"),
+    ("release_delay_ms", "--release-delay"),
+    ("reconcile_interval_ms", "--reconcile-interval"),
+    ("icon_on", "--icon-on"),
+    ("icon_off", "--icon-off"),
+    ("icon_locked", "--icon-locked"),
+    ("icon_missing", "--icon-missing"),
+    ("notify", "--notify"),
+    ("no_notify", "--no-notify"),
+    ("notify_urgency", "--notify-urgency"),
+    // Option names of the settings above.
+    ("tap_threshold", "--tap-threshold"),
+    ("press_delay", "--press-delay"),
+    ("release_delay", "--release-delay"),
+    ("reconcile_interval", "--reconcile-interval"),
+];
+
+#[derive(Deserialize)]
+struct Reply {
+    ok: bool,
+    status: Option<Status>,
+    error: Option<String>,
+}
+
+/// Whether `name` is a client subcommand rather than a daemon option.
+pub(crate) fn is_command(name: &str) -> bool {
+    matches!(
+        name,
+        "status"
+            | "on"
+            | "off"
+            | "mute"
+            | "unmute"
+            | "toggle"
+            | "lock"
+            | "unlock"
+            | "reload"
+            | "set"
+            | "watch"
+    )
+}
+
+/// Run a client subcommand; `args` starts with the subcommand name.
+pub(crate) fn run(args: &[String]) -> Result<()> {
+    let Some((name, rest)) = args.split_first() else {
+        bail!("missing subcommand");
+    };
+    let command = match name.as_str() {
+        "set" => return set(rest),
+        "watch" => return watch::run(rest),
+        "status" => Command::Status,
+        "on" | "unmute" => Command::On,
+        "off" | "mute" => Command::Off,
+        "toggle" => Command::Toggle,
+        "lock" => Command::LockMuted,
+        "unlock" => Command::Unlock,
+        "reload" => Command::Reload,
+        other => bail!("Unknown subcommand '{other}'"),
+    };
+    if !rest.is_empty() {
+        bail!("pttkey {name} takes no arguments");
+    }
+    let stream = connect()?;
+    let status = request(stream, &command)?;
+    print_status(&status);
+    Ok(())
+}
+
+/// Make sure the config just written gets used: a running daemon watches the file and
+/// reloads it by itself, so only restart the service when no daemon answers on the control
+/// socket. An extra reload request would make the daemon reload twice.
+pub(crate) fn reload_or_restart() {
+    if connect().is_ok() {
+        println!("The running pttkey reloads the config");
+    } else {
+        restart_service();
+    }
+}
+
+/// `pttkey set NAME=VALUE...`: change persisted settings and apply them to the running daemon.
+fn set(pairs: &[String]) -> Result<()> {
+    if pairs.is_empty() {
+        bail!("Usage: pttkey set NAME=VALUE... (e.g. pttkey set key=KEY_F9)");
+    }
+    let (mut base, _, _) = load_persisted_config()?;
+    let mut flags = Vec::new();
+    for pair in pairs {
+        let (name, value) = pair
+            .split_once('=')
+            .with_context(|| format!("expected NAME=VALUE, got '{pair}'"))?;
+        // Config file names and option names both work.
+        let name = name.trim().replace('-', "_");
+        let value = value.trim();
+        match name.as_str() {
+            "keys" | "key" => {
+                base.keys = value.split('+').map(|key| key.trim().to_string()).collect();
+                base.axis = None;
+                base.axis_press = None;
+                base.axis_release = None;
+                base.switch = None;
+            }
+            "axis" => {
+                set_primary_axis(&mut base, value)?;
+                base.keys.clear();
+                base.switch = None;
+            }
+            "switch" => {
+                base.switch = Some(value.to_string());
+                base.keys.clear();
+                base.axis = None;
+                base.axis_press = None;
+                base.axis_release = None;
+            }
+            "action" => base.action = value.to_string(),
+            "exclusive" => base.exclusive = parse_bool(&name, value)?,
+            "ordered" => base.ordered = parse_bool(&name, value)?,
+            "source" => base.source = (value != "default").then(|| value.to_string()),
+            name if FLAG_SETTINGS.contains(&name) => {
+                let flag = name.replace('_', "-");
+                if parse_bool(name, value)? {
+                    flags.push(format!("--{flag}"));
+                } else {
+                    flags.push(format!("--no-{flag}"));
+                }
+            }
+            name => {
+                let Some((_, flag)) = VALUE_SETTINGS.iter().find(|(known, _)| *known == name)
+                else {
+                    let valid = PRIMARY_SETTINGS
+                        .iter()
+                        .chain(&FLAG_SETTINGS)
+                        .chain(VALUE_SETTINGS.iter().map(|(known, _)| known))
+                        .copied()
+                        .collect::<Vec<_>>();
+                    bail!(
+                        "Unknown setting '{name}'. Valid names: {}",
+                        valid.join(", ")
+                    );
+                };
+                flags.push(flag.to_string());
+                flags.push(value.to_string());
+            }
+        }
+    }
+
+    let (config, _) = parse_args(base, &flags)?;
+    let persisted = persisted_from_config(&config);
+    let primary = config_path()?;
+    let backup = backup_config_path()?;
+    write_persisted_config(&persisted, &primary, &backup)?;
+    println!("Config saved");
+    reload_or_restart();
+    Ok(())
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool> {
+    match value {
+        "true" | "yes" | "on" => Ok(true),
+        "false" | "no" | "off" => Ok(false),
+        other => bail!("invalid value '{other}' for {name} (expected true or false)"),
+    }
+}
+
+pub(crate) fn connect() -> Result<UnixStream> {
+    let path = socket_path()?;
+    UnixStream::connect(&path).with_context(|| {
+        format!(
+            "pttkey is not running (no control socket at {})",
+            path.display()
+        )
+    })
+}
+
+/// Send one command and wait for its reply.
+fn request(mut stream: UnixStream, command: &Command) -> Result<Status> {
+    let mut line = serde_json::to_string(command)?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .context("Failed to send to the control socket")?;
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .context("Failed to read from the control socket")?;
+    let reply: Reply = serde_json::from_str(&reply).context("Invalid reply from pttkey")?;
+    match (reply.ok, reply.status) {
+        (true, Some(status)) => Ok(status),
+        _ => bail!(reply.error.unwrap_or_else(|| "request failed".to_string())),
+    }
+}
+
+fn print_status(status: &Status) {
+    let mic = if status.active { "on" } else { "muted" };
+    if status.locked {
+        println!("mic: {mic} (locked)");
+    } else {
+        println!("mic: {mic}");
+    }
+    println!("trigger: {}", status.trigger);
+    println!("mode: {}", status.mode);
+    println!("bindings: {}", status.bindings.join(", "));
+    if status.devices.is_empty() {
+        println!("devices: none");
+    } else {
+        println!("devices: {}", status.devices.join(", "));
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::{bail, Context, Result};
-use evdev::KeyCode;
+use evdev::{AbsoluteAxisCode, KeyCode, SwitchCode};
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs;
@@ -7,16 +7,26 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use crate::constants::{
-    CONFIG_BACKUP_NAME, CONFIG_DIR_NAME, CONFIG_FILE_NAME, SUPPORTED_KEYS,
+    CONFIG_BACKUP_NAME, CONFIG_DIR_NAME, CONFIG_FILE_NAME, KEY_ALIASES, KEY_CODE_MAX,
 };
 
 /// How the mic is toggled: by absolute volume level or by mute state.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub(crate) enum Mode {
     Volume,
     Mute,
 }
 
+/// Which tool or API is used to change the mic state.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Backend {
+    Auto,
+    Pipewire,
+    Wpctl,
+    Pactl,
+    Alsa,
+}
+
 /// Startup behavior for setting the mic state at launch.
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum StartupState {
@@ -24,6 +34,118 @@ pub(crate) enum StartupState {
     Unmuted,
 }
 
+/// How key presses drive the mic.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Trigger {
+    /// Mic is on while the keys are held.
+    Hold,
+    /// Each press of the keys flips the mic state.
+    Toggle,
+    /// A short tap latches the mic on until the next tap; a longer hold is push-to-talk.
+    Hybrid,
+}
+
+/// One capture source driven by the push-to-talk state.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MicTarget {
+    /// Source id, name or description; `None` = system default source.
+    pub(crate) source: Option<String>,
+    /// Volume vs mute behavior for this source.
+    pub(crate) mode: Mode,
+    /// Volume level when active.
+    pub(crate) on_level: f32,
+    /// Volume level when inactive.
+    pub(crate) off_level: f32,
+}
+
+/// What a binding does to the mic.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Action {
+    /// Follow `trigger` and `reverse`.
+    Trigger,
+    /// Mic is on while the keys are held.
+    Talk,
+    /// Mic is muted while the keys are held.
+    Mute,
+    /// Each press flips the mic state.
+    Toggle,
+    /// Each press mutes and locks the mic, or unlocks it again.
+    MuteLock,
+}
+
+/// An absolute axis (foot pedal, gamepad trigger) that counts as held past a threshold.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct AxisTrigger {
+    pub(crate) axis: AbsoluteAxisCode,
+    /// Position in the axis range (0.0 - 1.0) at which it counts as pressed.
+    pub(crate) press: f32,
+    /// Position at which it counts as released again. Below `press` for an axis
+    /// pressed towards its maximum, above it for one pressed towards its minimum.
+    pub(crate) release: f32,
+}
+
+/// A key chord; every key has to be held for the binding to fire.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Binding {
+    pub(crate) keys: Vec<KeyCode>,
+    pub(crate) action: Action,
+    /// Only fire while no other key is held (e.g. Ctrl+F but not Ctrl+Shift+F).
+    pub(crate) exclusive: bool,
+    /// The last key has to be pressed after all the others.
+    pub(crate) ordered: bool,
+    /// Axis that has to be pressed along with the keys.
+    pub(crate) axis: Option<AxisTrigger>,
+    /// Switch (e.g. `SW_MUTE_DEVICE`) that has to be on along with the keys.
+    pub(crate) switch: Option<SwitchCode>,
+}
+
+/// Stable device selection; every set field has to match.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct DeviceMatch {
+    /// Device name, exact or a glob with `*` and `?`.
+    pub(crate) name: Option<String>,
+    /// USB vendor and product id.
+    pub(crate) id: Option<(u16, u16)>,
+    /// Physical path (e.g. `usb-0000:00:14.0-2/input0`).
+    pub(crate) phys: Option<String>,
+    /// Unique identifier, usually a serial number.
+    pub(crate) uniq: Option<String>,
+}
+
+impl DeviceMatch {
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == DeviceMatch::default()
+    }
+}
+
+/// Urgency of a desktop notification.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+/// Whether and how one kind of event is shown as a desktop notification.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct NotifySetting {
+    pub(crate) enabled: bool,
+    pub(crate) urgency: Urgency,
+}
+
+/// Desktop notification settings per kind of event.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Notifications {
+    /// An input device was lost or connected.
+    pub(crate) device: NotifySetting,
+    /// The mic backend failed to change or restore the mic state.
+    pub(crate) backend: NotifySetting,
+    /// The config file or its sounds could not be loaded.
+    pub(crate) config: NotifySetting,
+    /// The mic turned on or off.
+    pub(crate) mic: NotifySetting,
+}
+
 #[derive(Clone, Debug)]
 pub(crate) enum SoundChoice {
     Default,
@@ -34,12 +156,24 @@ pub(crate) enum SoundChoice {
 /// Runtime configuration assembled from CLI arguments.
 #[derive(Clone, Debug)]
 pub(crate) struct Config {
-    /// Keys that must be held simultaneously to activate the mic.
-    pub(crate) keys: Vec<KeyCode>,
-    /// Optional explicit input device path (e.g. /dev/input/event7).
-    pub(crate) device_path: Option<PathBuf>,
+    /// Key chords that activate the mic; any one of them is enough. The first one is `keys`.
+    pub(crate) bindings: Vec<Binding>,
+    /// Explicit input device paths (e.g. /dev/input/event7); empty = auto-detect.
+    pub(crate) device_paths: Vec<PathBuf>,
+    /// Name/id/phys/uniq filter applied to input devices.
+    pub(crate) device_match: DeviceMatch,
     /// Volume vs mute behavior.
     pub(crate) mode: Mode,
+    /// Mic control backend (auto-detected when `Auto`).
+    pub(crate) backend: Backend,
+    /// ALSA card used by the alsa backend (e.g. `default` or `hw:1`).
+    pub(crate) alsa_card: String,
+    /// ALSA simple mixer control used by the alsa backend.
+    pub(crate) alsa_control: String,
+    /// Capture source to control (id, name or description); `None` = default source.
+    pub(crate) source: Option<String>,
+    /// All sources to control: the primary one above followed by extra targets.
+    pub(crate) targets: Vec<MicTarget>,
     /// Volume level when active.
     pub(crate) on_level: f32,
     /// Volume level when inactive.
@@ -52,10 +186,14 @@ pub(crate) struct Config {
     pub(crate) sound_off: SoundChoice,
     /// Volume for sound effects (0.0 - 1.0+).
     pub(crate) sound_volume: f32,
+    /// Interactively learn the key chord (`pttkey learn`) and exit.
+    pub(crate) learn: bool,
     /// Print available keys and exit.
     pub(crate) list_keys: bool,
     /// Print available input devices and exit.
     pub(crate) list_devices: bool,
+    /// Print capture sources of the backend and exit.
+    pub(crate) list_sources: bool,
     /// Print configuration and exit.
     pub(crate) print_config: bool,
     /// Validate inputs and exit without changing mic state.
@@ -64,8 +202,30 @@ pub(crate) struct Config {
     pub(crate) startup_state: StartupState,
     /// Reverse behavior so holding keys mutes instead of unmutes.
     pub(crate) reverse: bool,
+    /// Hold-to-talk or press-to-toggle.
+    pub(crate) trigger: Trigger,
+    /// Initial mic state in toggle and hybrid mode.
+    pub(crate) toggle_startup_state: StartupState,
+    /// Presses shorter than this latch the mic in hybrid mode.
+    pub(crate) tap_threshold_ms: u64,
+    /// How long the keys must be held before a press counts.
+    pub(crate) press_delay_ms: u64,
+    /// How long the mic stays on after it would turn off.
+    pub(crate) release_delay_ms: u64,
     /// Suppress configured key events from reaching other apps.
     pub(crate) suppress: bool,
+    /// How often to check for mic changes made outside pttkey (0 = never).
+    pub(crate) reconcile_interval_ms: u64,
+    /// Restore the mic state found at startup on exit instead of muting.
+    pub(crate) restore_on_exit: bool,
+    /// Icons `pttkey watch` shows for an active, muted and locked mic.
+    pub(crate) icon_on: String,
+    pub(crate) icon_off: String,
+    pub(crate) icon_locked: String,
+    /// Icon `pttkey watch` shows while no input device is attached or pttkey is not running.
+    pub(crate) icon_missing: String,
+    /// Desktop notifications per kind of event.
+    pub(crate) notifications: Notifications,
 }
 
 /// Config data persisted to disk.
@@ -73,8 +233,23 @@ pub(crate) struct Config {
 #[serde(default)]
 pub(crate) struct PersistedConfig {
     pub(crate) keys: Vec<String>,
-    pub(crate) device_path: Option<String>,
+    pub(crate) action: String,
+    pub(crate) exclusive: bool,
+    pub(crate) ordered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) axis: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) axis_press: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) axis_release: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) switch: Option<String>,
+    pub(crate) device_path: Option<DevicePathValue>,
     pub(crate) mode: String,
+    pub(crate) backend: String,
+    pub(crate) alsa_card: String,
+    pub(crate) alsa_control: String,
+    pub(crate) source: Option<String>,
     pub(crate) on_level: f32,
     pub(crate) off_level: f32,
     pub(crate) sounds: bool,
@@ -83,7 +258,116 @@ pub(crate) struct PersistedConfig {
     pub(crate) sound_volume: f32,
     pub(crate) startup_state: String,
     pub(crate) reverse: bool,
+    pub(crate) trigger: String,
+    pub(crate) toggle_startup_state: String,
+    pub(crate) tap_threshold_ms: u64,
+    pub(crate) press_delay_ms: u64,
+    pub(crate) release_delay_ms: u64,
     pub(crate) suppress: bool,
+    pub(crate) reconcile_interval_ms: u64,
+    pub(crate) restore_on_exit: bool,
+    pub(crate) icon_on: String,
+    pub(crate) icon_off: String,
+    pub(crate) icon_locked: String,
+    pub(crate) icon_missing: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) device: Option<PersistedDeviceMatch>,
+    pub(crate) notifications: PersistedNotifications,
+    /// Extra key chords; each one activates the mic on its own, like `keys`.
+    pub(crate) bindings: Vec<PersistedBinding>,
+    /// Extra sources; unset fields inherit the top-level mode and levels.
+    pub(crate) targets: Vec<PersistedTarget>,
+}
+
+/// An extra capture source persisted as a `[[targets]]` table.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct PersistedTarget {
+    pub(crate) source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) on_level: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) off_level: Option<f32>,
+}
+
+/// An extra key chord persisted as a `[[bindings]]` table.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct PersistedBinding {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) keys: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) action: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub(crate) exclusive: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub(crate) ordered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) axis: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) axis_press: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) axis_release: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) switch: Option<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// The `[device]` table selecting input devices by their properties.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct PersistedDeviceMatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
+    /// `vendor:product` in hex, e.g. `046d:c52b`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) phys: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) uniq: Option<String>,
+}
+
+/// The `[notifications]` table: a toggle and an urgency per kind of event.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct PersistedNotifications {
+    pub(crate) device: bool,
+    pub(crate) device_urgency: String,
+    pub(crate) backend: bool,
+    pub(crate) backend_urgency: String,
+    pub(crate) config: bool,
+    pub(crate) config_urgency: String,
+    pub(crate) mic: bool,
+    pub(crate) mic_urgency: String,
+}
+
+impl Default for PersistedNotifications {
+    fn default() -> Self {
+        Self {
+            device: false,
+            device_urgency: "normal".to_string(),
+            backend: false,
+            backend_urgency: "critical".to_string(),
+            config: false,
+            config_urgency: "normal".to_string(),
+            mic: false,
+            mic_urgency: "low".to_string(),
+        }
+    }
+}
+
+/// `device_path` as a single path or a list of paths.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum DevicePathValue {
+    One(String),
+    Many(Vec<String>),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -97,8 +381,19 @@ impl Default for PersistedConfig {
     fn default() -> Self {
         Self {
             keys: vec!["BTN_EXTRA".to_string()],
+            action: "trigger".to_string(),
+            exclusive: false,
+            ordered: false,
+            axis: None,
+            axis_press: None,
+            axis_release: None,
+            switch: None,
             device_path: None,
             mode: "volume".to_string(),
+            backend: "auto".to_string(),
+            alsa_card: "default".to_string(),
+            alsa_control: "Capture".to_string(),
+            source: None,
             on_level: 1.0,
             off_level: 0.0,
             sounds: true,
@@ -107,7 +402,22 @@ impl Default for PersistedConfig {
             sound_volume: 1.0,
             startup_state: "muted".to_string(),
             reverse: false,
+            trigger: "hold".to_string(),
+            toggle_startup_state: "muted".to_string(),
+            tap_threshold_ms: 300,
+            press_delay_ms: 0,
+            release_delay_ms: 0,
             suppress: false,
+            reconcile_interval_ms: 0,
+            restore_on_exit: false,
+            icon_on: "🎤".to_string(),
+            icon_off: "🔇".to_string(),
+            icon_locked: "🔒".to_string(),
+            icon_missing: "⚠".to_string(),
+            device: None,
+            notifications: PersistedNotifications::default(),
+            bindings: Vec::new(),
+            targets: Vec::new(),
         }
     }
 }
@@ -218,7 +528,7 @@ pub(crate) fn restart_service() {
     }
 }
 
-fn mode_label(mode: Mode) -> &'static str {
+pub(crate) fn mode_label(mode: Mode) -> &'static str {
     match mode {
         Mode::Volume => "volume",
         Mode::Mute => "mute",
@@ -233,6 +543,148 @@ fn parse_mode(value: &str) -> Result<Mode> {
     }
 }
 
+fn backend_label(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Auto => "auto",
+        Backend::Pipewire => "pipewire",
+        Backend::Wpctl => "wpctl",
+        Backend::Pactl => "pactl",
+        Backend::Alsa => "alsa",
+    }
+}
+
+fn parse_backend(value: &str) -> Result<Backend> {
+    match value {
+        "auto" => Ok(Backend::Auto),
+        "pipewire" => Ok(Backend::Pipewire),
+        "wpctl" => Ok(Backend::Wpctl),
+        "pactl" => Ok(Backend::Pactl),
+        "alsa" => Ok(Backend::Alsa),
+        _ => bail!(
+            "Invalid --backend '{value}'. Use 'auto', 'pipewire', 'wpctl', 'pactl' or 'alsa'."
+        ),
+    }
+}
+
+pub(crate) fn trigger_label(trigger: Trigger) -> &'static str {
+    match trigger {
+        Trigger::Hold => "hold",
+        Trigger::Toggle => "toggle",
+        Trigger::Hybrid => "hybrid",
+    }
+}
+
+fn parse_trigger(value: &str) -> Result<Trigger> {
+    match value {
+        "hold" => Ok(Trigger::Hold),
+        "toggle" => Ok(Trigger::Toggle),
+        "hybrid" => Ok(Trigger::Hybrid),
+        _ => bail!("Invalid --trigger '{value}'. Use 'hold', 'toggle' or 'hybrid'."),
+    }
+}
+
+pub(crate) fn action_label(action: Action) -> &'static str {
+    match action {
+        Action::Trigger => "trigger",
+        Action::Talk => "push-to-talk",
+        Action::Mute => "push-to-mute",
+        Action::Toggle => "toggle",
+        Action::MuteLock => "mute-lock",
+    }
+}
+
+fn parse_action(value: &str) -> Result<Action> {
+    match value {
+        "trigger" => Ok(Action::Trigger),
+        "push-to-talk" => Ok(Action::Talk),
+        "push-to-mute" => Ok(Action::Mute),
+        "toggle" => Ok(Action::Toggle),
+        "mute-lock" => Ok(Action::MuteLock),
+        _ => bail!(
+            "Invalid --action '{value}'. Use 'trigger', 'push-to-talk', 'push-to-mute', 'toggle' or 'mute-lock'."
+        ),
+    }
+}
+
+pub(crate) fn urgency_label(urgency: Urgency) -> &'static str {
+    match urgency {
+        Urgency::Low => "low",
+        Urgency::Normal => "normal",
+        Urgency::Critical => "critical",
+    }
+}
+
+fn parse_urgency(value: &str) -> Result<Urgency> {
+    match value {
+        "low" => Ok(Urgency::Low),
+        "normal" => Ok(Urgency::Normal),
+        "critical" => Ok(Urgency::Critical),
+        _ => bail!("Invalid urgency '{value}'. Use 'low', 'normal' or 'critical'."),
+    }
+}
+
+fn parse_notifications(persisted: &PersistedNotifications) -> Result<Notifications> {
+    let setting = |enabled: bool, urgency: &str| -> Result<NotifySetting> {
+        Ok(NotifySetting {
+            enabled,
+            urgency: parse_urgency(urgency)?,
+        })
+    };
+    Ok(Notifications {
+        device: setting(persisted.device, &persisted.device_urgency)?,
+        backend: setting(persisted.backend, &persisted.backend_urgency)?,
+        config: setting(persisted.config, &persisted.config_urgency)?,
+        mic: setting(persisted.mic, &persisted.mic_urgency)?,
+    })
+}
+
+fn persisted_notifications(notifications: &Notifications) -> PersistedNotifications {
+    PersistedNotifications {
+        device: notifications.device.enabled,
+        device_urgency: urgency_label(notifications.device.urgency).to_string(),
+        backend: notifications.backend.enabled,
+        backend_urgency: urgency_label(notifications.backend.urgency).to_string(),
+        config: notifications.config.enabled,
+        config_urgency: urgency_label(notifications.config.urgency).to_string(),
+        mic: notifications.mic.enabled,
+        mic_urgency: urgency_label(notifications.mic.urgency).to_string(),
+    }
+}
+
+fn notify_setting<'a>(
+    notifications: &'a mut Notifications,
+    category: &str,
+) -> Result<&'a mut NotifySetting> {
+    match category {
+        "device" => Ok(&mut notifications.device),
+        "backend" => Ok(&mut notifications.backend),
+        "config" => Ok(&mut notifications.config),
+        "mic" => Ok(&mut notifications.mic),
+        _ => bail!(
+            "Invalid notification category '{category}'. Use 'device', 'backend', 'config', 'mic' or 'all'."
+        ),
+    }
+}
+
+/// Enabled notification categories with their urgency, e.g. `device (normal), mic (low)`.
+fn notifications_label(notifications: &Notifications) -> String {
+    let enabled: Vec<String> = [
+        ("device", notifications.device),
+        ("backend", notifications.backend),
+        ("config", notifications.config),
+        ("mic", notifications.mic),
+    ]
+    .into_iter()
+    .filter(|(_, setting)| setting.enabled)
+    .map(|(category, setting)| format!("{category} ({})", urgency_label(setting.urgency)))
+    .collect();
+    if enabled.is_empty() {
+        "off".to_string()
+    } else {
+        enabled.join(", ")
+    }
+}
+
 fn startup_state_label(state: StartupState) -> &'static str {
     match state {
         StartupState::Muted => "muted",
@@ -250,12 +702,25 @@ fn parse_startup_state(value: &str) -> Result<StartupState> {
 
 pub(crate) fn persisted_from_config(config: &Config) -> PersistedConfig {
     PersistedConfig {
-        keys: config.keys.iter().map(|k| key_label(*k)).collect(),
-        device_path: config
-            .device_path
-            .as_ref()
-            .map(|p| p.display().to_string()),
+        keys: config.bindings[0]
+            .keys
+            .iter()
+            .map(|k| key_label(*k))
+            .collect(),
+        action: action_label(config.bindings[0].action).to_string(),
+        exclusive: config.bindings[0].exclusive,
+        ordered: config.bindings[0].ordered,
+        axis: config.bindings[0].axis.map(|axis| axis_name(axis.axis)),
+        axis_press: config.bindings[0].axis.map(|axis| axis.press),
+        axis_release: config.bindings[0].axis.map(|axis| axis.release),
+        switch: config.bindings[0].switch.map(switch_name),
+        device_path: device_path_value(&config.device_paths),
+        device: persisted_device_match(&config.device_match),
         mode: mode_label(config.mode).to_string(),
+        backend: backend_label(config.backend).to_string(),
+        alsa_card: config.alsa_card.clone(),
+        alsa_control: config.alsa_control.clone(),
+        source: config.source.clone(),
         on_level: config.on_level,
         off_level: config.off_level,
         sounds: config.sounds,
@@ -264,27 +729,206 @@ pub(crate) fn persisted_from_config(config: &Config) -> PersistedConfig {
         sound_volume: config.sound_volume,
         startup_state: startup_state_label(config.startup_state).to_string(),
         reverse: config.reverse,
+        trigger: trigger_label(config.trigger).to_string(),
+        toggle_startup_state: startup_state_label(config.toggle_startup_state).to_string(),
+        tap_threshold_ms: config.tap_threshold_ms,
+        press_delay_ms: config.press_delay_ms,
+        release_delay_ms: config.release_delay_ms,
         suppress: config.suppress,
+        reconcile_interval_ms: config.reconcile_interval_ms,
+        restore_on_exit: config.restore_on_exit,
+        icon_on: config.icon_on.clone(),
+        icon_off: config.icon_off.clone(),
+        icon_locked: config.icon_locked.clone(),
+        icon_missing: config.icon_missing.clone(),
+        notifications: persisted_notifications(&config.notifications),
+        bindings: config
+            .bindings
+            .iter()
+            .skip(1)
+            .map(|binding| PersistedBinding {
+                keys: binding.keys.iter().map(|k| key_label(*k)).collect(),
+                action: (binding.action != Action::Trigger)
+                    .then(|| action_label(binding.action).to_string()),
+                exclusive: binding.exclusive,
+                ordered: binding.ordered,
+                axis: binding.axis.map(|axis| axis_name(axis.axis)),
+                axis_press: binding.axis.map(|axis| axis.press),
+                axis_release: binding.axis.map(|axis| axis.release),
+                switch: binding.switch.map(switch_name),
+            })
+            .collect(),
+        targets: config
+            .targets
+            .iter()
+            .skip(1)
+            .map(|target| persisted_target(config, target))
+            .collect(),
+    }
+}
+
+fn persisted_target(config: &Config, target: &MicTarget) -> PersistedTarget {
+    PersistedTarget {
+        source: target.source.clone().unwrap_or_else(|| "default".to_string()),
+        mode: (target.mode != config.mode).then(|| mode_label(target.mode).to_string()),
+        on_level: (target.on_level != config.on_level).then_some(target.on_level),
+        off_level: (target.off_level != config.off_level).then_some(target.off_level),
+    }
+}
+
+/// Build the target list from the primary source settings and extra targets.
+fn build_targets(
+    source: &Option<String>,
+    mode: Mode,
+    on_level: f32,
+    off_level: f32,
+    extra: &[PersistedTarget],
+) -> Result<Vec<MicTarget>> {
+    let mut targets = vec![MicTarget {
+        source: source.clone(),
+        mode,
+        on_level,
+        off_level,
+    }];
+    for target in extra {
+        targets.push(MicTarget {
+            source: (target.source != "default").then(|| target.source.clone()),
+            mode: target.mode.as_deref().map(parse_mode).transpose()?.unwrap_or(mode),
+            on_level: target.on_level.unwrap_or(on_level),
+            off_level: target.off_level.unwrap_or(off_level),
+        });
+    }
+    Ok(targets)
+}
+
+/// Build the binding list from the top-level binding settings and extra bindings.
+fn build_bindings(base: &PersistedConfig) -> Result<Vec<Binding>> {
+    let mut primary = base
+        .keys
+        .iter()
+        .map(|k| parse_key(k))
+        .collect::<Result<Vec<_>>>()?;
+    let axis = parse_axis_trigger(base.axis.as_deref(), base.axis_press, base.axis_release)?;
+    let switch = base.switch.as_deref().map(parse_switch).transpose()?;
+    if primary.is_empty() && axis.is_none() && switch.is_none() {
+        primary.push(KeyCode::BTN_EXTRA);
+    }
+    let mut bindings = vec![Binding {
+        keys: primary,
+        action: parse_action(&base.action)?,
+        exclusive: base.exclusive,
+        ordered: base.ordered,
+        axis,
+        switch,
+    }];
+    for binding in &base.bindings {
+        if binding.keys.is_empty() && binding.axis.is_none() && binding.switch.is_none() {
+            bail!("A [[bindings]] entry has no keys, axis or switch");
+        }
+        bindings.push(Binding {
+            keys: binding
+                .keys
+                .iter()
+                .map(|k| parse_key(k))
+                .collect::<Result<Vec<_>>>()?,
+            action: binding
+                .action
+                .as_deref()
+                .map(parse_action)
+                .transpose()?
+                .unwrap_or(Action::Trigger),
+            exclusive: binding.exclusive,
+            ordered: binding.ordered,
+            axis: parse_axis_trigger(
+                binding.axis.as_deref(),
+                binding.axis_press,
+                binding.axis_release,
+            )?,
+            switch: binding.switch.as_deref().map(parse_switch).transpose()?,
+        });
+    }
+    Ok(bindings)
+}
+
+/// Key names, axis and switch of a binding joined with `+`.
+pub(crate) fn binding_label(binding: &Binding) -> String {
+    let mut parts: Vec<String> = binding.keys.iter().map(|k| key_label(*k)).collect();
+    if let Some(axis) = binding.axis {
+        parts.push(format!(
+            "{}:{}:{}",
+            axis_name(axis.axis),
+            axis.press,
+            axis.release
+        ));
     }
+    parts.extend(binding.switch.map(switch_name));
+    parts.join("+")
+}
+
+/// Short human readable name of a target's source.
+pub(crate) fn target_label(target: &MicTarget) -> &str {
+    target.source.as_deref().unwrap_or("default")
 }
 
 pub(crate) fn print_persisted_config(path: &Path, config: &PersistedConfig) {
-    let keys = if config.keys.is_empty() {
+    let keys = if config.keys.is_empty() && config.axis.is_none() && config.switch.is_none() {
         "BTN_EXTRA".to_string()
     } else {
         config.keys.join("+")
     };
     println!("config_path: {}", path.display());
     println!("config_keys: {}", keys);
+    if let Some(axis) = &config.axis {
+        println!("config_axis: {axis}");
+    }
+    if let Some(switch) = &config.switch {
+        println!("config_switch: {switch}");
+    }
+    println!("config_action: {}", config.action);
+    println!("config_exclusive: {}", config.exclusive);
+    println!("config_ordered: {}", config.ordered);
+    for binding in &config.bindings {
+        let mut inputs = binding.keys.clone();
+        inputs.extend(binding.axis.clone());
+        inputs.extend(binding.switch.clone());
+        println!(
+            "config_binding: {} ({}{}{})",
+            inputs.join("+"),
+            binding.action.as_deref().unwrap_or("trigger"),
+            if binding.exclusive { ", exclusive" } else { "" },
+            if binding.ordered { ", ordered" } else { "" }
+        );
+    }
     println!(
         "config_device: {}",
-        config
-            .device_path
-            .as_deref()
-            .unwrap_or("auto")
+        device_paths_label(&parse_device_paths(config.device_path.clone()))
+    );
+    println!(
+        "config_device_match: {}",
+        parse_device_match(config.device.clone())
+            .map(|device| device_match_label(&device))
+            .unwrap_or_else(|err| err.to_string())
     );
     println!("config_mode: {}", config.mode);
+    println!("config_backend: {}", config.backend);
+    println!("config_alsa_card: {}", config.alsa_card);
+    println!("config_alsa_control: {}", config.alsa_control);
+    println!(
+        "config_source: {}",
+        config.source.as_deref().unwrap_or("default")
+    );
+    for target in &config.targets {
+        println!("config_target: {}", target.source);
+    }
     println!("config_reverse: {}", config.reverse);
+    println!("config_trigger: {}", config.trigger);
+    println!(
+        "config_toggle_startup_state: {}",
+        config.toggle_startup_state
+    );
+    println!("config_tap_threshold_ms: {}", config.tap_threshold_ms);
+    println!("config_press_delay_ms: {}", config.press_delay_ms);
+    println!("config_release_delay_ms: {}", config.release_delay_ms);
     println!("config_on_level: {}", config.on_level);
     println!("config_off_level: {}", config.off_level);
     println!("config_sounds: {}", config.sounds);
@@ -299,6 +943,120 @@ pub(crate) fn print_persisted_config(path: &Path, config: &PersistedConfig) {
     println!("config_sound_volume: {}", config.sound_volume);
     println!("config_startup_state: {}", config.startup_state);
     println!("config_suppress: {}", config.suppress);
+    println!(
+        "config_reconcile_interval_ms: {}",
+        config.reconcile_interval_ms
+    );
+    println!("config_restore_on_exit: {}", config.restore_on_exit);
+    println!("config_icon_on: {}", config.icon_on);
+    println!("config_icon_off: {}", config.icon_off);
+    println!("config_icon_locked: {}", config.icon_locked);
+    println!("config_icon_missing: {}", config.icon_missing);
+    let notifications = &config.notifications;
+    for (category, enabled, urgency) in [
+        (
+            "device",
+            notifications.device,
+            &notifications.device_urgency,
+        ),
+        (
+            "backend",
+            notifications.backend,
+            &notifications.backend_urgency,
+        ),
+        (
+            "config",
+            notifications.config,
+            &notifications.config_urgency,
+        ),
+        ("mic", notifications.mic, &notifications.mic_urgency),
+    ] {
+        println!("config_notify_{category}: {enabled} ({urgency})");
+    }
+}
+
+fn parse_device_paths(value: Option<DevicePathValue>) -> Vec<PathBuf> {
+    match value {
+        None => Vec::new(),
+        Some(DevicePathValue::One(path)) => vec![PathBuf::from(path)],
+        Some(DevicePathValue::Many(paths)) => paths.into_iter().map(PathBuf::from).collect(),
+    }
+}
+
+fn device_path_value(paths: &[PathBuf]) -> Option<DevicePathValue> {
+    let mut labels: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+    match labels.len() {
+        0 => None,
+        1 => labels.pop().map(DevicePathValue::One),
+        _ => Some(DevicePathValue::Many(labels)),
+    }
+}
+
+fn parse_device_id(value: &str) -> Result<(u16, u16)> {
+    let parsed = value.split_once(':').and_then(|(vendor, product)| {
+        Some((
+            u16::from_str_radix(vendor, 16).ok()?,
+            u16::from_str_radix(product, 16).ok()?,
+        ))
+    });
+    parsed.with_context(|| {
+        format!("Invalid device id '{value}'. Use vendor:product in hex, e.g. 046d:c52b.")
+    })
+}
+
+fn parse_device_match(value: Option<PersistedDeviceMatch>) -> Result<DeviceMatch> {
+    let value = value.unwrap_or_default();
+    Ok(DeviceMatch {
+        name: value.name,
+        id: value.id.as_deref().map(parse_device_id).transpose()?,
+        phys: value.phys,
+        uniq: value.uniq,
+    })
+}
+
+fn persisted_device_match(device: &DeviceMatch) -> Option<PersistedDeviceMatch> {
+    if device.is_empty() {
+        return None;
+    }
+    Some(PersistedDeviceMatch {
+        name: device.name.clone(),
+        id: device
+            .id
+            .map(|(vendor, product)| format!("{vendor:04x}:{product:04x}")),
+        phys: device.phys.clone(),
+        uniq: device.uniq.clone(),
+    })
+}
+
+pub(crate) fn device_match_label(device: &DeviceMatch) -> String {
+    let mut parts = Vec::new();
+    if let Some(name) = &device.name {
+        parts.push(format!("name={name}"));
+    }
+    if let Some((vendor, product)) = device.id {
+        parts.push(format!("id={vendor:04x}:{product:04x}"));
+    }
+    if let Some(phys) = &device.phys {
+        parts.push(format!("phys={phys}"));
+    }
+    if let Some(uniq) = &device.uniq {
+        parts.push(format!("uniq={uniq}"));
+    }
+    if parts.is_empty() {
+        return "any".to_string();
+    }
+    parts.join(", ")
+}
+
+fn device_paths_label(paths: &[PathBuf]) -> String {
+    if paths.is_empty() {
+        return "auto".to_string();
+    }
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 fn sound_setting_value(setting: &SoundChoice) -> Option<SoundSettingValue> {
@@ -343,13 +1101,120 @@ fn parse_key(input: &str) -> Result<KeyCode> {
         return Ok(KeyCode::new(code));
     }
 
-    for (name, key) in SUPPORTED_KEYS {
-        if *name == normalized {
+    // Kernel names as-is, then without their KEY_/BTN_ prefix (e.g. `f9`, `side`).
+    for candidate in [
+        normalized.clone(),
+        format!("KEY_{normalized}"),
+        format!("BTN_{normalized}"),
+    ] {
+        if let Ok(key) = candidate.parse::<KeyCode>() {
+            return Ok(key);
+        }
+    }
+
+    for (alias, key) in KEY_ALIASES {
+        if alias.eq_ignore_ascii_case(&normalized) {
             return Ok(*key);
         }
     }
 
-    bail!("Unknown key '{input}'. Use a numeric key code or a known name like BTN_EXTRA/KEY_F9.")
+    bail!("Unknown key '{input}'. Use a numeric key code, a kernel name like BTN_EXTRA/KEY_F9 or an alias like mouse5/ctrl/f9 (see --list-keys).")
+}
+
+fn parse_axis(input: &str) -> Result<AbsoluteAxisCode> {
+    let normalized = input.trim().to_ascii_uppercase();
+    if let Ok(code) = normalized.parse::<u16>() {
+        return Ok(AbsoluteAxisCode(code));
+    }
+    for candidate in [normalized.clone(), format!("ABS_{normalized}")] {
+        if let Ok(axis) = candidate.parse::<AbsoluteAxisCode>() {
+            return Ok(axis);
+        }
+    }
+    bail!("Unknown axis '{input}'. Use a numeric axis code or a kernel name like ABS_Z/ABS_GAS.")
+}
+
+fn axis_name(axis: AbsoluteAxisCode) -> String {
+    let name = format!("{axis:?}");
+    if name.starts_with("unknown") {
+        return axis.0.to_string();
+    }
+    name
+}
+
+fn parse_switch(input: &str) -> Result<SwitchCode> {
+    let normalized = input.trim().to_ascii_uppercase();
+    if let Ok(code) = normalized.parse::<u16>() {
+        return Ok(SwitchCode(code));
+    }
+    for candidate in [normalized.clone(), format!("SW_{normalized}")] {
+        if let Ok(switch) = candidate.parse::<SwitchCode>() {
+            return Ok(switch);
+        }
+    }
+    bail!(
+        "Unknown switch '{input}'. Use a numeric switch code or a kernel name like SW_MUTE_DEVICE."
+    )
+}
+
+fn switch_name(switch: SwitchCode) -> String {
+    let name = format!("{switch:?}");
+    if name.starts_with("unknown") {
+        return switch.0.to_string();
+    }
+    name
+}
+
+/// Build an axis trigger; the release point defaults to 0.1 before the press point.
+fn parse_axis_trigger(
+    axis: Option<&str>,
+    press: Option<f32>,
+    release: Option<f32>,
+) -> Result<Option<AxisTrigger>> {
+    let Some(axis) = axis else {
+        return Ok(None);
+    };
+    let press = press.unwrap_or(0.5);
+    // Rounded so the default does not show up as 0.59999996.
+    let release = release.unwrap_or(((press - 0.1).max(0.0) * 1000.0).round() / 1000.0);
+    for value in [press, release] {
+        if !(0.0..=1.0).contains(&value) {
+            bail!("Invalid axis threshold {value}. Use a value between 0.0 and 1.0.");
+        }
+    }
+    Ok(Some(AxisTrigger {
+        axis: parse_axis(axis)?,
+        press,
+        release,
+    }))
+}
+
+/// Parse `--axis NAME[:PRESS[:RELEASE]]`.
+fn parse_axis_arg(input: &str) -> Result<AxisTrigger> {
+    let mut parts = input.split(':');
+    let axis = parts.next().unwrap_or_default();
+    let mut threshold = |name: &str| {
+        parts
+            .next()
+            .map(|value| {
+                value
+                    .parse::<f32>()
+                    .with_context(|| format!("invalid --axis {name} '{value}'"))
+            })
+            .transpose()
+    };
+    let press = threshold("press")?;
+    let release = threshold("release")?;
+    parse_axis_trigger(Some(axis), press, release)?.context("missing value for --axis")
+}
+
+/// Store `NAME[:PRESS[:RELEASE]]` as the axis of the main binding.
+pub(crate) fn set_primary_axis(base: &mut PersistedConfig, value: &str) -> Result<()> {
+    let trigger = parse_axis_arg(value)?;
+    base.axis = Some(axis_name(trigger.axis));
+    base.axis_press = Some(trigger.press);
+    base.axis_release = Some(trigger.release);
+    Ok(())
 }
 
 fn parse_keys(input: &str) -> Result<Vec<KeyCode>> {
@@ -363,26 +1228,85 @@ fn print_help() {
     println!(
         "pttkey\n\
 Usage: pttkey [options]\n\
+       pttkey learn [options]\n\
+       pttkey <status|on|off|mute|unmute|toggle|lock|unlock|reload>\n\
+       pttkey set NAME=VALUE...\n\
+       pttkey watch [--format waybar|i3blocks|plain]\n\
+\n\
+Commands:\n\
+  learn               press the key chord to use and save it (and optionally the device) to the config\n\
+  status              print the state of the running pttkey\n\
+  on, unmute          leave the mic on while no binding is held\n\
+  off, mute           leave the mic muted while no binding is held\n\
+  toggle              switch between on and off\n\
+  lock, unlock        lock the mic muted, or release the lock\n\
+  reload              make the running pttkey re-read the config\n\
+  set NAME=VALUE...   change options by name (e.g. set key=KEY_F9) and apply them without a restart\n\
+  watch               print a status bar line on every mic change (--format waybar, i3blocks or plain)\n\
 \n\
 Options:\n\
-  --key <NAME|CODE>   evdev key name or numeric code; can repeat or use '+'\n\
-                      (e.g. --key KEY_LEFTCTRL+KEY_F or --key KEY_LEFTCTRL --key KEY_F)\n\
-  --device <PATH>     use a specific input device (e.g. /dev/input/event7)\n\
+  --key <NAME|CODE>   evdev key name or numeric code; join with '+' for a chord\n\
+                      (e.g. --key KEY_LEFTCTRL+KEY_F); repeat to add alternative bindings\n\
+  --axis <NAME>[:<PRESS>[:<RELEASE>]]  add a binding on an absolute axis such as a pedal\n\
+                      or trigger; thresholds are positions in the axis range from 0.0 to 1.0\n\
+                      (default: press 0.5, release 0.1 below press)\n\
+  --switch <NAME>     add a binding that is held while a switch such as SW_MUTE_DEVICE or\n\
+                      SW_HEADPHONE_INSERT is on\n\
+  --action <trigger|push-to-talk|push-to-mute|toggle|mute-lock>  what the preceding\n\
+                      --key/--axis/--switch does (default: trigger, which follows\n\
+                      --trigger and --reverse)\n\
+  --exclusive         the preceding --key only fires while no other key is held\n\
+  --no-exclusive      extra held keys do not block the preceding --key (default)\n\
+  --ordered           the last key of the preceding --key must be pressed last\n\
+  --no-ordered        the keys of the preceding --key may go down in any order (default)\n\
+  --device <PATH>     use a specific input device (e.g. /dev/input/event7); can repeat\n\
+                      (default: every device that supports the configured keys)\n\
+  --device-name <NAME|GLOB>  only use devices with this name ('*' and '?' allowed)\n\
+  --device-id <VENDOR:PRODUCT>  only use devices with this USB id (hex, e.g. 046d:c52b)\n\
+  --device-phys <PHYS>  only use devices with this physical path\n\
+  --device-uniq <UNIQ>  only use devices with this unique id (serial)\n\
+                      ('any' clears a --device-* filter)\n\
   --mode <volume|mute>  toggle by volume level or set-mute (default: volume)\n\
+  --backend <auto|pipewire|wpctl|pactl|alsa>  mic control backend (default: auto)\n\
+  --alsa-card <NAME>  ALSA card for the alsa backend (default: default)\n\
+  --alsa-control <NAME>  ALSA mixer control for the alsa backend (default: Capture)\n\
+  --source <NAME|DESC|ID>  capture source to control ('default' follows the system default);\n\
+                      repeat to control several sources at once\n\
   --reverse           invert behavior so holding the key mutes\n\
   --no-reverse        disable reverse behavior\n\
+  --trigger <hold|toggle|hybrid>  hold the key to talk, press it to flip the mic,\n\
+                      or tap to latch / hold to talk (default: hold)\n\
+  --toggle-startup-state <muted|unmuted>  initial mic state in toggle/hybrid mode (default: muted)\n\
+  --tap-threshold <MS>  longest press that counts as a tap in hybrid mode (default: 300)\n\
+  --press-delay <MS>  ignore presses shorter than MS ms (default: 0)\n\
+  --release-delay <MS>  keep the mic on for MS ms after release (default: 0)\n\
   --on-level <FLOAT>  volume level when pressed (default: 1.0)\n\
   --off-level <FLOAT> volume level when released (default: 0.0)\n\
   --sound-on <PATH>   custom sound file for mic on (mp3/wav/ogg)\n\
   --sound-off <PATH>  custom sound file for mic off (mp3/wav/ogg)\n\
   --sound-volume <FLOAT>  sound volume (default: 1.0)\n\
-  --startup-state <muted|unmuted>  initial mic state (default: muted)\n\
+  --startup-state <muted|unmuted>  initial mic state in hold mode, kept until the\n\
+                      first press (default: muted)\n\
   --suppress          suppress only the configured key(s) from reaching other apps\n\
   --no-suppress       do not suppress key events (default)\n\
+  --reconcile-interval <MS>  re-check and restore the mic state every MS ms\n\
+                      if changed outside pttkey (default: 0, disabled)\n\
   --sounds            enable on/off sounds (default)\n\
   --no-sounds         disable on/off sounds\n\
+  --restore-on-exit   restore the mic volume/mute found at startup on exit\n\
+  --no-restore-on-exit  mute the mic on exit (default)\n\
+  --icon-on <TEXT>    icon 'pttkey watch' shows while the mic is on (default: 🎤)\n\
+  --icon-off <TEXT>   icon 'pttkey watch' shows while the mic is muted (default: 🔇)\n\
+  --icon-locked <TEXT>  icon 'pttkey watch' shows while the mic is locked muted (default: 🔒)\n\
+  --icon-missing <TEXT>  icon 'pttkey watch' shows without input device or daemon (default: ⚠)\n\
+  --notify <LIST>     show desktop notifications for device, backend, config and/or mic\n\
+                      events (comma separated, or 'all'; default: none)\n\
+  --no-notify <LIST>  stop notifications for these categories\n\
+  --notify-urgency <CATEGORY=LEVEL>  low, normal or critical (defaults: device=normal,\n\
+                      backend=critical, config=normal, mic=low)\n\
   --list-keys         print supported key names and exit\n\
   --list-devices      print input devices and exit\n\
+  --list-sources      print capture sources of the backend and exit\n\
   --print-config      print parsed configuration and exit\n\
   --dry-run           validate configuration and exit without changing mic state\n\
   -h, --help          show this help\n\
@@ -391,48 +1315,89 @@ Examples:\n\
   pttkey --key BTN_EXTRA\n\
   pttkey --key KEY_F9 --mode mute --no-sounds\n\
   pttkey --key KEY_LEFTCTRL+KEY_F --mode mute\n\
+  pttkey --key BTN_EXTRA --key KEY_LEFTCTRL+KEY_F\n\
+  pttkey --key KEY_LEFTCTRL+KEY_F --exclusive --ordered\n\
+  pttkey --axis ABS_Z:0.6:0.4\n\
+  pttkey --switch SW_MUTE_DEVICE --action push-to-mute\n\
+  pttkey --key BTN_EXTRA --action push-to-talk --key KEY_F9 --action toggle --key KEY_PAUSE --action mute-lock\n\
   pttkey --key KEY_F9 --reverse --startup-state unmuted\n\
+  pttkey --key KEY_F9 --trigger toggle\n\
+  pttkey --key BTN_EXTRA --trigger hybrid --tap-threshold 250\n\
   pttkey --sound-on ~/on.wav --sound-off ~/off.ogg\n\
   pttkey --device /dev/input/event7 --key KEY_SPACE\n\
+  pttkey --device-name 'Logitech*' --device-id 046d:c52b\n\
+  pttkey --backend alsa --alsa-card hw:1 --alsa-control Mic --mode mute\n\
 \n\
 Config:\n\
-  ~/.config/pttkey/config.toml (auto-created, CLI updates and restarts service)\n"
+  ~/.config/pttkey/config.toml (auto-created; CLI flags update it and a running pttkey\n\
+  reloads it, otherwise the user service is restarted)\n\
+\n\
+Control:\n\
+  $XDG_RUNTIME_DIR/pttkey.sock accepts JSON lines such as {{\"command\":\"toggle\"}}\n\
+  (status, on, off, toggle, lock-muted, unlock, reload, subscribe)\n"
     );
 }
 
-pub(crate) fn print_supported_keys() {
-    for (name, _) in SUPPORTED_KEYS {
-        println!("{name}");
-    }
+/// Kernel name of a key code, if evdev knows one.
+fn key_name(key: KeyCode) -> Option<String> {
+    let name = format!("{key:?}");
+    (!name.starts_with("unknown")).then_some(name)
 }
 
-fn key_label(key: KeyCode) -> String {
-    for (name, k) in SUPPORTED_KEYS {
-        if *k == key {
-            return (*name).to_string();
+pub(crate) fn print_supported_keys() {
+    for code in 1..=KEY_CODE_MAX {
+        if let Some(name) = key_name(KeyCode::new(code)) {
+            println!("{name}");
         }
     }
-    format!("{}", key.code())
+    println!();
+    println!("Aliases (case-insensitive; KEY_/BTN_ prefixes may also be left out):");
+    for (alias, key) in KEY_ALIASES {
+        println!("{alias} = {}", key_label(*key));
+    }
+}
+
+pub(crate) fn key_label(key: KeyCode) -> String {
+    key_name(key).unwrap_or_else(|| format!("{}", key.code()))
 }
 
 pub(crate) fn print_config(config: &Config) {
-    let keys = config
-        .keys
-        .iter()
-        .map(|k| key_label(*k))
-        .collect::<Vec<_>>()
-        .join("+");
-    let device = config
-        .device_path
-        .as_ref()
-        .map(|p| p.display().to_string())
-        .unwrap_or_else(|| "auto".to_string());
+    let device = device_paths_label(&config.device_paths);
     let mode = mode_label(config.mode);
     let startup_state = startup_state_label(config.startup_state);
-    println!("keys: {keys}");
+    for binding in &config.bindings {
+        println!(
+            "binding: {} ({}{}{})",
+            binding_label(binding),
+            action_label(binding.action),
+            if binding.exclusive { ", exclusive" } else { "" },
+            if binding.ordered { ", ordered" } else { "" }
+        );
+    }
     println!("device: {device}");
+    println!("device_match: {}", device_match_label(&config.device_match));
     println!("mode: {mode}");
+    println!("backend: {}", backend_label(config.backend));
+    println!("alsa_card: {}", config.alsa_card);
+    println!("alsa_control: {}", config.alsa_control);
+    for target in &config.targets {
+        println!(
+            "target: {} (mode {}, on_level {}, off_level {})",
+            target_label(target),
+            mode_label(target.mode),
+            target.on_level,
+            target.off_level
+        );
+    }
     println!("reverse: {}", config.reverse);
+    println!("trigger: {}", trigger_label(config.trigger));
+    println!(
+        "toggle_startup_state: {}",
+        startup_state_label(config.toggle_startup_state)
+    );
+    println!("tap_threshold_ms: {}", config.tap_threshold_ms);
+    println!("press_delay_ms: {}", config.press_delay_ms);
+    println!("release_delay_ms: {}", config.release_delay_ms);
     println!("on_level: {}", config.on_level);
     println!("off_level: {}", config.off_level);
     println!("sounds: {}", config.sounds);
@@ -441,21 +1406,34 @@ pub(crate) fn print_config(config: &Config) {
     println!("sound_volume: {}", config.sound_volume);
     println!("startup_state: {startup_state}");
     println!("suppress: {}", config.suppress);
+    println!("reconcile_interval_ms: {}", config.reconcile_interval_ms);
+    println!("restore_on_exit: {}", config.restore_on_exit);
+    println!(
+        "icons: on {}, off {}, locked {}, missing {}",
+        config.icon_on, config.icon_off, config.icon_locked, config.icon_missing
+    );
+    println!(
+        "notifications: {}",
+        notifications_label(&config.notifications)
+    );
 }
 
 pub(crate) fn config_from_persisted(base: PersistedConfig) -> Result<Config> {
-    let mut keys: Vec<KeyCode> = base
-        .keys
-        .iter()
-        .map(|k| parse_key(k))
-        .collect::<Result<Vec<_>>>()?;
-    if keys.is_empty() {
-        keys.push(KeyCode::BTN_EXTRA);
-    }
+    let bindings = build_bindings(&base)?;
 
-    let device_path = base.device_path.map(PathBuf::from);
+    let device_paths = parse_device_paths(base.device_path);
+    let device_match = parse_device_match(base.device)?;
     let mode = parse_mode(&base.mode)?;
+    let backend = parse_backend(&base.backend)?;
+    let alsa_card = base.alsa_card;
+    let alsa_control = base.alsa_control;
+    let source = base.source;
     let reverse = base.reverse;
+    let trigger = parse_trigger(&base.trigger)?;
+    let toggle_startup_state = parse_startup_state(&base.toggle_startup_state)?;
+    let tap_threshold_ms = base.tap_threshold_ms;
+    let press_delay_ms = base.press_delay_ms;
+    let release_delay_ms = base.release_delay_ms;
     let on_level = base.on_level;
     let off_level = base.off_level;
     let sounds = base.sounds;
@@ -464,6 +1442,14 @@ pub(crate) fn config_from_persisted(base: PersistedConfig) -> Result<Config> {
     let sound_volume = base.sound_volume;
     let startup_state = parse_startup_state(&base.startup_state)?;
     let suppress = base.suppress;
+    let reconcile_interval_ms = base.reconcile_interval_ms;
+    let restore_on_exit = base.restore_on_exit;
+    let icon_on = base.icon_on;
+    let icon_off = base.icon_off;
+    let icon_locked = base.icon_locked;
+    let icon_missing = base.icon_missing;
+    let notifications = parse_notifications(&base.notifications)?;
+    let targets = build_targets(&source, mode, on_level, off_level, &base.targets)?;
 
     if let SoundChoice::File(path) = &sound_on {
         if !path.exists() {
@@ -477,54 +1463,91 @@ pub(crate) fn config_from_persisted(base: PersistedConfig) -> Result<Config> {
     }
 
     Ok(Config {
-        keys,
-        device_path,
+        bindings,
+        device_paths,
+        device_match,
         mode,
+        backend,
+        alsa_card,
+        alsa_control,
+        source,
+        targets,
         reverse,
+        trigger,
+        toggle_startup_state,
+        tap_threshold_ms,
+        press_delay_ms,
+        release_delay_ms,
         on_level,
         off_level,
         sounds,
         sound_on,
         sound_off,
         sound_volume,
+        learn: false,
         list_keys: false,
         list_devices: false,
+        list_sources: false,
         print_config: false,
         dry_run: false,
         startup_state,
         suppress,
+        reconcile_interval_ms,
+        restore_on_exit,
+        icon_on,
+        icon_off,
+        icon_locked,
+        icon_missing,
+        notifications,
     })
 }
 
-pub(crate) fn parse_args(base: PersistedConfig) -> Result<(Config, bool)> {
-    let mut keys: Vec<KeyCode> = base
-        .keys
-        .iter()
-        .map(|k| parse_key(k))
-        .collect::<Result<Vec<_>>>()?;
-    if keys.is_empty() {
-        keys.push(KeyCode::BTN_EXTRA);
-    }
-    let mut device_path = base.device_path.map(PathBuf::from);
+pub(crate) fn parse_args(base: PersistedConfig, args: &[String]) -> Result<(Config, bool)> {
+    let mut bindings = build_bindings(&base)?;
+    let mut device_paths = parse_device_paths(base.device_path);
+    let mut device_set = false;
+    let mut device_match = parse_device_match(base.device)?;
     let mut mode = parse_mode(&base.mode)?;
+    let mut backend = parse_backend(&base.backend)?;
+    let mut alsa_card = base.alsa_card;
+    let mut alsa_control = base.alsa_control;
+    let mut source = base.source;
+    let mut extra_targets = base.targets;
+    let mut source_set = false;
     let mut reverse = base.reverse;
+    let mut trigger = parse_trigger(&base.trigger)?;
+    let mut toggle_startup_state = parse_startup_state(&base.toggle_startup_state)?;
+    let mut tap_threshold_ms = base.tap_threshold_ms;
+    let mut press_delay_ms = base.press_delay_ms;
+    let mut release_delay_ms = base.release_delay_ms;
     let mut on_level = base.on_level;
     let mut off_level = base.off_level;
     let mut sounds = base.sounds;
     let mut sound_on = parse_sound_setting(base.sound_on);
     let mut sound_off = parse_sound_setting(base.sound_off);
     let mut sound_volume = base.sound_volume;
+    let mut learn = false;
     let mut list_keys = false;
     let mut list_devices = false;
+    let mut list_sources = false;
     let mut print_config = false;
     let mut dry_run = false;
     let mut startup_state = parse_startup_state(&base.startup_state)?;
     let mut startup_state_set = false;
     let mut suppress = base.suppress;
+    let mut reconcile_interval_ms = base.reconcile_interval_ms;
+    let mut restore_on_exit = base.restore_on_exit;
+    let mut icon_on = base.icon_on;
+    let mut icon_off = base.icon_off;
+    let mut icon_locked = base.icon_locked;
+    let mut icon_missing = base.icon_missing;
+    let mut notifications = parse_notifications(&base.notifications)?;
     let mut persist_changed = false;
     let mut key_set = false;
+    // Binding that --action, --exclusive and --ordered apply to: the one of the preceding
+    // --key/--axis/--switch, else the main one.
+    let mut binding_index = 0;
 
-    let args: Vec<String> = env::args().skip(1).collect();
     let mut i = 0;
     while i < args.len() {
         match args[i].as_str() {
@@ -535,18 +1558,110 @@ pub(crate) fn parse_args(base: PersistedConfig) -> Result<(Config, bool)> {
             "--key" => {
                 i += 1;
                 let value = args.get(i).context("missing value for --key")?;
-                let mut parsed = parse_keys(value)?;
+                let keys = parse_keys(value)?;
+                if !key_set {
+                    bindings.clear();
+                    key_set = true;
+                }
+                bindings.push(Binding {
+                    keys,
+                    action: Action::Trigger,
+                    exclusive: false,
+                    ordered: false,
+                    axis: None,
+                    switch: None,
+                });
+                binding_index = bindings.len() - 1;
+                persist_changed = true;
+            }
+            "--axis" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --axis")?;
+                let axis = parse_axis_arg(value)?;
+                if !key_set {
+                    bindings.clear();
+                    key_set = true;
+                }
+                bindings.push(Binding {
+                    keys: Vec::new(),
+                    action: Action::Trigger,
+                    exclusive: false,
+                    ordered: false,
+                    axis: Some(axis),
+                    switch: None,
+                });
+                binding_index = bindings.len() - 1;
+                persist_changed = true;
+            }
+            "--switch" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --switch")?;
+                let switch = parse_switch(value)?;
                 if !key_set {
-                    keys.clear();
+                    bindings.clear();
                     key_set = true;
                 }
-                keys.append(&mut parsed);
+                bindings.push(Binding {
+                    keys: Vec::new(),
+                    action: Action::Trigger,
+                    exclusive: false,
+                    ordered: false,
+                    axis: None,
+                    switch: Some(switch),
+                });
+                binding_index = bindings.len() - 1;
+                persist_changed = true;
+            }
+            "--action" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --action")?;
+                bindings[binding_index].action = parse_action(value)?;
+                persist_changed = true;
+            }
+            "--exclusive" | "--no-exclusive" => {
+                bindings[binding_index].exclusive = args[i] == "--exclusive";
+                persist_changed = true;
+            }
+            "--ordered" | "--no-ordered" => {
+                bindings[binding_index].ordered = args[i] == "--ordered";
                 persist_changed = true;
             }
             "--device" => {
                 i += 1;
                 let value = args.get(i).context("missing value for --device")?;
-                device_path = Some(PathBuf::from(value));
+                if !device_set {
+                    device_paths.clear();
+                    device_set = true;
+                }
+                device_paths.push(PathBuf::from(value));
+                persist_changed = true;
+            }
+            "--device-name" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --device-name")?;
+                device_match.name = (value != "any").then(|| value.clone());
+                persist_changed = true;
+            }
+            "--device-id" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --device-id")?;
+                device_match.id = if value == "any" {
+                    None
+                } else {
+                    Some(parse_device_id(value)?)
+                };
+                persist_changed = true;
+            }
+            "--device-phys" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --device-phys")?;
+                device_match.phys = (value != "any").then(|| value.clone());
+                persist_changed = true;
+            }
+            "--device-uniq" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --device-uniq")?;
+                device_match.uniq = (value != "any").then(|| value.clone());
                 persist_changed = true;
             }
             "--mode" => {
@@ -555,6 +1670,39 @@ pub(crate) fn parse_args(base: PersistedConfig) -> Result<(Config, bool)> {
                 mode = parse_mode(value)?;
                 persist_changed = true;
             }
+            "--backend" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --backend")?;
+                backend = parse_backend(value)?;
+                persist_changed = true;
+            }
+            "--alsa-card" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --alsa-card")?;
+                alsa_card = value.clone();
+                persist_changed = true;
+            }
+            "--alsa-control" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --alsa-control")?;
+                alsa_control = value.clone();
+                persist_changed = true;
+            }
+            "--source" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --source")?;
+                if source_set {
+                    extra_targets.push(PersistedTarget {
+                        source: value.clone(),
+                        ..PersistedTarget::default()
+                    });
+                } else {
+                    source = (value != "default").then(|| value.clone());
+                    extra_targets.clear();
+                    source_set = true;
+                }
+                persist_changed = true;
+            }
             "--reverse" => {
                 reverse = true;
                 persist_changed = true;
@@ -563,6 +1711,44 @@ pub(crate) fn parse_args(base: PersistedConfig) -> Result<(Config, bool)> {
                 reverse = false;
                 persist_changed = true;
             }
+            "--trigger" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --trigger")?;
+                trigger = parse_trigger(value)?;
+                persist_changed = true;
+            }
+            "--toggle-startup-state" => {
+                i += 1;
+                let value = args
+                    .get(i)
+                    .context("missing value for --toggle-startup-state")?;
+                toggle_startup_state = parse_startup_state(value)?;
+                persist_changed = true;
+            }
+            "--tap-threshold" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --tap-threshold")?;
+                tap_threshold_ms = value
+                    .parse::<u64>()
+                    .with_context(|| format!("invalid --tap-threshold '{value}'"))?;
+                persist_changed = true;
+            }
+            "--press-delay" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --press-delay")?;
+                press_delay_ms = value
+                    .parse::<u64>()
+                    .with_context(|| format!("invalid --press-delay '{value}'"))?;
+                persist_changed = true;
+            }
+            "--release-delay" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --release-delay")?;
+                release_delay_ms = value
+                    .parse::<u64>()
+                    .with_context(|| format!("invalid --release-delay '{value}'"))?;
+                persist_changed = true;
+            }
             "--on-level" => {
                 i += 1;
                 let value = args.get(i).context("missing value for --on-level")?;
@@ -630,12 +1816,94 @@ pub(crate) fn parse_args(base: PersistedConfig) -> Result<(Config, bool)> {
                 suppress = false;
                 persist_changed = true;
             }
+            "--reconcile-interval" => {
+                i += 1;
+                let value = args
+                    .get(i)
+                    .context("missing value for --reconcile-interval")?;
+                reconcile_interval_ms = value
+                    .parse::<u64>()
+                    .with_context(|| format!("invalid --reconcile-interval '{value}'"))?;
+                persist_changed = true;
+            }
+            "--restore-on-exit" => {
+                restore_on_exit = true;
+                persist_changed = true;
+            }
+            "--no-restore-on-exit" => {
+                restore_on_exit = false;
+                persist_changed = true;
+            }
+            "--icon-on" => {
+                i += 1;
+                icon_on = args.get(i).context("missing value for --icon-on")?.clone();
+                persist_changed = true;
+            }
+            "--icon-off" => {
+                i += 1;
+                icon_off = args.get(i).context("missing value for --icon-off")?.clone();
+                persist_changed = true;
+            }
+            "--icon-locked" => {
+                i += 1;
+                icon_locked = args
+                    .get(i)
+                    .context("missing value for --icon-locked")?
+                    .clone();
+                persist_changed = true;
+            }
+            "--icon-missing" => {
+                i += 1;
+                icon_missing = args
+                    .get(i)
+                    .context("missing value for --icon-missing")?
+                    .clone();
+                persist_changed = true;
+            }
+            "--notify" | "--no-notify" => {
+                let enabled = args[i] == "--notify";
+                i += 1;
+                let value = args
+                    .get(i)
+                    .with_context(|| format!("missing value for {}", args[i - 1]))?;
+                for category in value.split(',').map(str::trim) {
+                    if category == "all" {
+                        for setting in [
+                            &mut notifications.device,
+                            &mut notifications.backend,
+                            &mut notifications.config,
+                            &mut notifications.mic,
+                        ] {
+                            setting.enabled = enabled;
+                        }
+                    } else {
+                        notify_setting(&mut notifications, category)?.enabled = enabled;
+                    }
+                }
+                persist_changed = true;
+            }
+            "--notify-urgency" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --notify-urgency")?;
+                let (category, urgency) = value.split_once('=').with_context(|| {
+                    format!("invalid --notify-urgency '{value}', expected CATEGORY=LEVEL")
+                })?;
+                notify_setting(&mut notifications, category.trim())?.urgency =
+                    parse_urgency(urgency.trim())?;
+                persist_changed = true;
+            }
+            "learn" if i == 0 => {
+                learn = true;
+            }
             "--list-keys" => {
                 list_keys = true;
             }
             "--list-devices" => {
                 list_devices = true;
             }
+            "--list-sources" => {
+                list_sources = true;
+            }
             "--print-config" => {
                 print_config = true;
             }
@@ -662,25 +1930,160 @@ pub(crate) fn parse_args(base: PersistedConfig) -> Result<(Config, bool)> {
         startup_state = StartupState::Unmuted;
     }
 
+    let targets = build_targets(&source, mode, on_level, off_level, &extra_targets)?;
+
     Ok((
         Config {
-            keys,
-            device_path,
+            bindings,
+            device_paths,
+            device_match,
             mode,
+            backend,
+            alsa_card,
+            alsa_control,
+            source,
+            targets,
             reverse,
+            trigger,
+            toggle_startup_state,
+            tap_threshold_ms,
+            press_delay_ms,
+            release_delay_ms,
             on_level,
             off_level,
             sounds,
             sound_on,
             sound_off,
             sound_volume,
+            learn,
             list_keys,
             list_devices,
+            list_sources,
             print_config,
             dry_run,
             startup_state,
             suppress,
+            reconcile_interval_ms,
+            restore_on_exit,
+            icon_on,
+            icon_off,
+            icon_locked,
+            icon_missing,
+            notifications,
         },
         persist_changed,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_kernel_names() {
+        assert_eq!(parse_key("KEY_F9").unwrap(), KeyCode::KEY_F9);
+        assert_eq!(parse_key("BTN_EXTRA").unwrap(), KeyCode::BTN_EXTRA);
+        assert_eq!(parse_key("key_leftctrl").unwrap(), KeyCode::KEY_LEFTCTRL);
+        assert_eq!(parse_key("  KEY_SPACE ").unwrap(), KeyCode::KEY_SPACE);
+    }
+
+    #[test]
+    fn parses_names_without_prefix() {
+        assert_eq!(parse_key("f9").unwrap(), KeyCode::KEY_F9);
+        assert_eq!(parse_key("Space").unwrap(), KeyCode::KEY_SPACE);
+        // KEY_ is tried before BTN_.
+        assert_eq!(parse_key("side").unwrap(), KeyCode::BTN_SIDE);
+        assert_eq!(parse_key("left").unwrap(), KeyCode::KEY_LEFT);
+    }
+
+    #[test]
+    fn parses_numeric_codes() {
+        assert_eq!(parse_key("276").unwrap(), KeyCode::BTN_EXTRA);
+        assert_eq!(parse_key("67").unwrap(), KeyCode::KEY_F9);
+    }
+
+    #[test]
+    fn resolves_every_alias() {
+        for (alias, key) in KEY_ALIASES {
+            assert_eq!(parse_key(alias).unwrap(), *key, "alias {alias}");
+            assert_eq!(
+                parse_key(&alias.to_ascii_lowercase()).unwrap(),
+                *key,
+                "alias {alias}"
+            );
+        }
+        assert_eq!(parse_key("MOUSE5").unwrap(), KeyCode::BTN_EXTRA);
+        assert_eq!(parse_key("btn_a").unwrap(), KeyCode::BTN_SOUTH);
+    }
+
+    #[test]
+    fn key_labels_parse_back() {
+        for code in 1..=KEY_CODE_MAX {
+            let key = KeyCode::new(code);
+            assert_eq!(parse_key(&key_label(key)).unwrap(), key, "code {code}");
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(parse_key("").is_err());
+        assert!(parse_key("KEY_NOPE").is_err());
+        assert!(parse_key("mouse9").is_err());
+    }
+
+    #[test]
+    fn action_applies_to_the_preceding_binding() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let base = PersistedConfig {
+            bindings: vec![PersistedBinding {
+                keys: vec!["KEY_F9".to_string()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let (config, _) = parse_args(base.clone(), &args(&["--action", "toggle"])).unwrap();
+        assert_eq!(config.bindings[0].action, Action::Toggle);
+        assert_eq!(config.bindings[1].action, Action::Trigger);
+
+        let (config, _) = parse_args(
+            base,
+            &args(&["--key", "KEY_F1", "--action", "toggle", "--key", "KEY_F2"]),
+        )
+        .unwrap();
+        assert_eq!(config.bindings.len(), 2);
+        assert_eq!(config.bindings[0].action, Action::Toggle);
+        assert_eq!(config.bindings[1].action, Action::Trigger);
+    }
+
+    #[test]
+    fn exclusive_and_ordered_apply_to_the_preceding_binding() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let base = PersistedConfig {
+            bindings: vec![PersistedBinding {
+                keys: vec!["KEY_LEFTCTRL".to_string(), "KEY_F9".to_string()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let (config, _) = parse_args(base.clone(), &args(&["--exclusive", "--ordered"])).unwrap();
+        assert!(config.bindings[0].exclusive && config.bindings[0].ordered);
+        assert!(!config.bindings[1].exclusive && !config.bindings[1].ordered);
+
+        let (config, _) = parse_args(
+            base,
+            &args(&[
+                "--key",
+                "KEY_F1",
+                "--key",
+                "KEY_F2",
+                "--exclusive",
+                "--ordered",
+            ]),
+        )
+        .unwrap();
+        assert!(!config.bindings[0].exclusive && !config.bindings[0].ordered);
+        assert!(config.bindings[1].exclusive && config.bindings[1].ordered);
+    }
+}
@@ -8,151 +8,60 @@ pub(crate) const DEFAULT_SOUND_OFF_WAV: &[u8] = include_bytes!("../mute.wav");
 pub(crate) const CONFIG_FILE_NAME: &str = "config.toml";
 pub(crate) const CONFIG_DIR_NAME: &str = "pttkey";
 pub(crate) const CONFIG_BACKUP_NAME: &str = ".pttkey-config.toml";
+/// Control socket created in `$XDG_RUNTIME_DIR`.
+pub(crate) const SOCKET_NAME: &str = "pttkey.sock";
+/// Well-known name and interface of the session bus service.
+pub(crate) const DBUS_NAME: &str = "io.github.wuild.PttKey";
+pub(crate) const DBUS_PATH: &str = "/io/github/wuild/PttKey";
 
-macro_rules! key_map {
-    ($($key:ident),+ $(,)?) => {
-        &[
-            $(
-                (stringify!($key), KeyCode::$key)
-            ),+
-        ]
-    };
-}
+/// Highest evdev key/button code (`KEY_MAX` in linux/input-event-codes.h).
+pub(crate) const KEY_CODE_MAX: u16 = 0x2ff;
 
-pub(crate) const SUPPORTED_KEYS: &[(&str, KeyCode)] = key_map![
-    // Mouse buttons,
-    BTN_LEFT,
-    BTN_RIGHT,
-    BTN_MIDDLE,
-    BTN_SIDE,
-    BTN_EXTRA,
-    BTN_FORWARD,
-    BTN_BACK,
-    // Letters,
-    KEY_A,
-    KEY_B,
-    KEY_C,
-    KEY_D,
-    KEY_E,
-    KEY_F,
-    KEY_G,
-    KEY_H,
-    KEY_I,
-    KEY_J,
-    KEY_K,
-    KEY_L,
-    KEY_M,
-    KEY_N,
-    KEY_O,
-    KEY_P,
-    KEY_Q,
-    KEY_R,
-    KEY_S,
-    KEY_T,
-    KEY_U,
-    KEY_V,
-    KEY_W,
-    KEY_X,
-    KEY_Y,
-    KEY_Z,
-    // Numbers row,
-    KEY_0,
-    KEY_1,
-    KEY_2,
-    KEY_3,
-    KEY_4,
-    KEY_5,
-    KEY_6,
-    KEY_7,
-    KEY_8,
-    KEY_9,
-    // Function keys,
-    KEY_F1,
-    KEY_F2,
-    KEY_F3,
-    KEY_F4,
-    KEY_F5,
-    KEY_F6,
-    KEY_F7,
-    KEY_F8,
-    KEY_F9,
-    KEY_F10,
-    KEY_F11,
-    KEY_F12,
-    KEY_F13,
-    KEY_F14,
-    KEY_F15,
-    KEY_F16,
-    KEY_F17,
-    KEY_F18,
-    KEY_F19,
-    KEY_F20,
-    KEY_F21,
-    KEY_F22,
-    KEY_F23,
-    KEY_F24,
-    // Modifiers and whitespace,
-    KEY_LEFTCTRL,
-    KEY_RIGHTCTRL,
-    KEY_LEFTSHIFT,
-    KEY_RIGHTSHIFT,
-    KEY_LEFTALT,
-    KEY_RIGHTALT,
-    KEY_LEFTMETA,
-    KEY_RIGHTMETA,
-    KEY_CAPSLOCK,
-    KEY_TAB,
-    KEY_SPACE,
-    KEY_ENTER,
-    KEY_ESC,
-    KEY_BACKSPACE,
-    // Navigation,
-    KEY_UP,
-    KEY_DOWN,
-    KEY_LEFT,
-    KEY_RIGHT,
-    KEY_HOME,
-    KEY_END,
-    KEY_PAGEUP,
-    KEY_PAGEDOWN,
-    KEY_INSERT,
-    KEY_DELETE,
-    // Punctuation,
-    KEY_MINUS,
-    KEY_EQUAL,
-    KEY_LEFTBRACE,
-    KEY_RIGHTBRACE,
-    KEY_BACKSLASH,
-    KEY_SEMICOLON,
-    KEY_APOSTROPHE,
-    KEY_GRAVE,
-    KEY_COMMA,
-    KEY_DOT,
-    KEY_SLASH,
-    // Numpad,
-    KEY_NUMLOCK,
-    KEY_KPSLASH,
-    KEY_KPASTERISK,
-    KEY_KPMINUS,
-    KEY_KPPLUS,
-    KEY_KPENTER,
-    KEY_KP0,
-    KEY_KP1,
-    KEY_KP2,
-    KEY_KP3,
-    KEY_KP4,
-    KEY_KP5,
-    KEY_KP6,
-    KEY_KP7,
-    KEY_KP8,
-    KEY_KP9,
-    KEY_KPDOT,
-    // Media,
-    KEY_MUTE,
-    KEY_VOLUMEDOWN,
-    KEY_VOLUMEUP,
-    KEY_PLAYPAUSE,
-    KEY_NEXTSONG,
-    KEY_PREVIOUSSONG,
-    KEY_STOPCD,
+/// Case-insensitive short names accepted in addition to the kernel key names.
+/// Names without a prefix (`f9`, `space`, `side`) also resolve to `KEY_*`/`BTN_*`.
+pub(crate) const KEY_ALIASES: &[(&str, KeyCode)] = &[
+    ("mouse1", KeyCode::BTN_LEFT),
+    ("mouse2", KeyCode::BTN_RIGHT),
+    ("mouse3", KeyCode::BTN_MIDDLE),
+    ("mouse4", KeyCode::BTN_SIDE),
+    ("mouse5", KeyCode::BTN_EXTRA),
+    ("ctrl", KeyCode::KEY_LEFTCTRL),
+    ("control", KeyCode::KEY_LEFTCTRL),
+    ("lctrl", KeyCode::KEY_LEFTCTRL),
+    ("rctrl", KeyCode::KEY_RIGHTCTRL),
+    ("shift", KeyCode::KEY_LEFTSHIFT),
+    ("lshift", KeyCode::KEY_LEFTSHIFT),
+    ("rshift", KeyCode::KEY_RIGHTSHIFT),
+    ("alt", KeyCode::KEY_LEFTALT),
+    ("lalt", KeyCode::KEY_LEFTALT),
+    ("ralt", KeyCode::KEY_RIGHTALT),
+    ("altgr", KeyCode::KEY_RIGHTALT),
+    ("meta", KeyCode::KEY_LEFTMETA),
+    ("super", KeyCode::KEY_LEFTMETA),
+    ("win", KeyCode::KEY_LEFTMETA),
+    ("lmeta", KeyCode::KEY_LEFTMETA),
+    ("rmeta", KeyCode::KEY_RIGHTMETA),
+    ("return", KeyCode::KEY_ENTER),
+    ("escape", KeyCode::KEY_ESC),
+    ("del", KeyCode::KEY_DELETE),
+    ("ins", KeyCode::KEY_INSERT),
+    ("pgup", KeyCode::KEY_PAGEUP),
+    ("pgdn", KeyCode::KEY_PAGEDOWN),
+    ("capslock", KeyCode::KEY_CAPSLOCK),
+    // Kernel names that share a code with another name and are missing from evdev's table.
+    ("BTN_MISC", KeyCode::BTN_0),
+    ("BTN_MOUSE", KeyCode::BTN_LEFT),
+    ("BTN_JOYSTICK", KeyCode::BTN_TRIGGER),
+    ("BTN_GAMEPAD", KeyCode::BTN_SOUTH),
+    ("BTN_A", KeyCode::BTN_SOUTH),
+    ("BTN_B", KeyCode::BTN_EAST),
+    ("BTN_X", KeyCode::BTN_NORTH),
+    ("BTN_Y", KeyCode::BTN_WEST),
+    ("BTN_DIGI", KeyCode::BTN_TOOL_PEN),
+    ("BTN_WHEEL", KeyCode::BTN_GEAR_DOWN),
+    ("BTN_TRIGGER_HAPPY", KeyCode::BTN_TRIGGER_HAPPY1),
+    ("KEY_HANGUEL", KeyCode::KEY_HANGEUL),
+    ("KEY_SCREENLOCK", KeyCode::KEY_COFFEE),
+    ("KEY_WIMAX", KeyCode::KEY_WWAN),
+    ("KEY_BRIGHTNESS_ZERO", KeyCode::KEY_BRIGHTNESS_AUTO),
 ];
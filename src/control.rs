@@ -0,0 +1,223 @@
+//! Control socket: line-delimited JSON commands from scripts and status bars.
+//!
+//! Each request is one line like `{"command":"toggle"}`. Every request is answered with
+//! `{"ok":true,"status":{...}}` or `{"ok":false,"error":"..."}`; after `subscribe` the
+//! client also receives `{"event":"status","status":{...}}` whenever the status changes.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+
+use crate::constants::SOCKET_NAME;
+
+/// Longest request line accepted; clients that send more without a newline are dropped.
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// A request sent over the control socket.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub(crate) enum Command {
+    Status,
+    On,
+    Off,
+    Toggle,
+    LockMuted,
+    Unlock,
+    Reload,
+    Subscribe,
+}
+
+/// State reported to clients.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct Status {
+    pub(crate) active: bool,
+    pub(crate) locked: bool,
+    pub(crate) trigger: String,
+    pub(crate) mode: String,
+    pub(crate) bindings: Vec<String>,
+    pub(crate) devices: Vec<String>,
+}
+
+struct Client {
+    id: u64,
+    stream: UnixStream,
+    /// Bytes received after the last complete line.
+    buf: Vec<u8>,
+    subscribed: bool,
+    /// The peer closed its end; dropped once pending replies are sent.
+    closed: bool,
+}
+
+pub(crate) struct ControlServer {
+    listener: UnixListener,
+    path: PathBuf,
+    clients: Vec<Client>,
+    next_id: u64,
+    /// Status last sent to subscribers.
+    published: Option<Status>,
+}
+
+/// `$XDG_RUNTIME_DIR/pttkey.sock`.
+pub(crate) fn socket_path() -> Result<PathBuf> {
+    let dir = env::var_os("XDG_RUNTIME_DIR").context("XDG_RUNTIME_DIR not set")?;
+    Ok(PathBuf::from(dir).join(SOCKET_NAME))
+}
+
+impl ControlServer {
+    pub(crate) fn new() -> Result<Self> {
+        let path = socket_path()?;
+        if UnixStream::connect(&path).is_ok() {
+            bail!("another pttkey is already listening on {}", path.display());
+        }
+        // Left behind by a daemon that did not shut down cleanly.
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Failed to create control socket {}", path.display()))?;
+        listener.set_nonblocking(true)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        Ok(Self {
+            listener,
+            path,
+            clients: Vec::new(),
+            next_id: 0,
+            published: None,
+        })
+    }
+
+    /// The listening socket and every connected client, for the poll set.
+    pub(crate) fn fds(&self) -> Vec<RawFd> {
+        let mut fds = vec![self.listener.as_raw_fd()];
+        fds.extend(
+            self.clients
+                .iter()
+                .filter(|c| !c.closed)
+                .map(|c| c.stream.as_raw_fd()),
+        );
+        fds
+    }
+
+    /// Accept new clients and return the complete requests received since the last call.
+    pub(crate) fn read_requests(&mut self) -> Vec<(u64, Result<Command, String>)> {
+        self.clients.retain(|c| !c.closed);
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_err() {
+                continue;
+            }
+            self.clients.push(Client {
+                id: self.next_id,
+                stream,
+                buf: Vec::new(),
+                subscribed: false,
+                closed: false,
+            });
+            self.next_id += 1;
+        }
+
+        let mut requests = Vec::new();
+        for client in self.clients.iter_mut().filter(|c| !c.closed) {
+            let mut chunk = [0u8; 1024];
+            loop {
+                match client.stream.read(&mut chunk) {
+                    Ok(0) => {
+                        client.closed = true;
+                        // A subscriber that hung up is not listening any more.
+                        client.subscribed = false;
+                        break;
+                    }
+                    Ok(len) => {
+                        client.buf.extend_from_slice(&chunk[..len]);
+                        let pending = client
+                            .buf
+                            .iter()
+                            .rev()
+                            .position(|b| *b == b'\n')
+                            .unwrap_or(client.buf.len());
+                        if pending > MAX_LINE_LEN {
+                            eprintln!(
+                                "Control client sent a request over {MAX_LINE_LEN} bytes, dropping it"
+                            );
+                            let _ = client.stream.shutdown(Shutdown::Both);
+                            client.buf.clear();
+                            client.closed = true;
+                            client.subscribed = false;
+                            break;
+                        }
+                    }
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(_) => {
+                        client.closed = true;
+                        client.subscribed = false;
+                        break;
+                    }
+                }
+            }
+            // A last request without a trailing newline still counts.
+            if client.closed && !client.buf.is_empty() {
+                client.buf.push(b'\n');
+            }
+            while let Some(end) = client.buf.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = client.buf.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let request = serde_json::from_str(line.trim()).map_err(|err| err.to_string());
+                requests.push((client.id, request));
+            }
+        }
+        requests
+    }
+
+    /// Send status updates to this client from now on.
+    pub(crate) fn subscribe(&mut self, id: u64) {
+        if let Some(client) = self.clients.iter_mut().find(|c| c.id == id) {
+            client.subscribed = true;
+        }
+    }
+
+    pub(crate) fn reply(&mut self, id: u64, reply: Result<&Status, String>) {
+        let message = match reply {
+            Ok(status) => json!({ "ok": true, "status": status }),
+            Err(error) => json!({ "ok": false, "error": error }),
+        };
+        if let Some(client) = self.clients.iter_mut().find(|c| c.id == id) {
+            send(client, &message);
+        }
+    }
+
+    /// Tell subscribers about the status if it changed since the last call.
+    pub(crate) fn publish(&mut self, status: &Status) {
+        if self.published.as_ref() == Some(status) {
+            return;
+        }
+        self.published = Some(status.clone());
+        let message = json!({ "event": "status", "status": status });
+        for client in self.clients.iter_mut().filter(|c| c.subscribed) {
+            send(client, &message);
+        }
+    }
+}
+
+/// Write one JSON line; a client that cannot take it is dropped.
+fn send(client: &mut Client, message: &Value) {
+    let mut line = message.to_string();
+    line.push('\n');
+    if client.stream.write_all(line.as_bytes()).is_err() {
+        client.closed = true;
+        client.subscribed = false;
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
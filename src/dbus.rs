@@ -0,0 +1,166 @@
+//! Session bus service: mic state as properties plus `SetActive`, `Toggle` and `Reload`.
+//!
+//! zbus answers calls on its own thread; they are queued for the main loop, which is woken
+//! through the shared `Waker`. Properties and `StateChanged` follow the status the main loop
+//! publishes after each iteration.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use zbus::blocking::connection::Builder;
+use zbus::blocking::Connection;
+use zbus::interface;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::Value;
+
+use crate::constants::{DBUS_NAME, DBUS_PATH};
+use crate::control::{Command, Status};
+use crate::event_loop::Waker;
+
+struct Service {
+    status: Status,
+    requests: Sender<Command>,
+    waker: Arc<Waker>,
+}
+
+impl Service {
+    fn request(&self, command: Command) {
+        if self.requests.send(command).is_ok() {
+            self.waker.wake();
+        }
+    }
+}
+
+#[interface(name = "io.github.wuild.PttKey")]
+impl Service {
+    /// Leave the mic on or muted while no binding is held.
+    fn set_active(&self, active: bool) {
+        self.request(if active { Command::On } else { Command::Off });
+    }
+
+    fn toggle(&self) {
+        self.request(Command::Toggle);
+    }
+
+    /// Re-read the config file.
+    fn reload(&self) {
+        self.request(Command::Reload);
+    }
+
+    #[zbus(property)]
+    fn active(&self) -> bool {
+        self.status.active
+    }
+
+    #[zbus(property)]
+    fn mode(&self) -> String {
+        self.status.mode.clone()
+    }
+
+    /// One label per binding, e.g. `KEY_LEFTCTRL+KEY_F` or `ABS_Z:0.6:0.4`.
+    #[zbus(property)]
+    fn keys(&self) -> Vec<String> {
+        self.status.bindings.clone()
+    }
+
+    #[zbus(property)]
+    fn locked(&self) -> bool {
+        self.status.locked
+    }
+
+    #[zbus(signal)]
+    async fn state_changed(
+        emitter: &SignalEmitter<'_>,
+        active: bool,
+        locked: bool,
+    ) -> zbus::Result<()>;
+}
+
+pub(crate) struct DbusService {
+    connection: Connection,
+    requests: Receiver<Command>,
+    /// Status the properties currently report.
+    published: Status,
+}
+
+impl DbusService {
+    /// Claim the bus name on the session bus (`$DBUS_SESSION_BUS_ADDRESS`).
+    pub(crate) fn new(status: Status, waker: Arc<Waker>) -> Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let service = Service {
+            status: status.clone(),
+            requests: tx,
+            waker,
+        };
+        let connection = Builder::session()
+            .and_then(|builder| builder.name(DBUS_NAME))
+            .and_then(|builder| builder.serve_at(DBUS_PATH, service))
+            .and_then(|builder| builder.build())
+            .with_context(|| format!("Failed to register {DBUS_NAME} on the session bus"))?;
+        Ok(Self {
+            connection,
+            requests: rx,
+            published: status,
+        })
+    }
+
+    /// Method calls received since the last call.
+    pub(crate) fn take_requests(&self) -> Vec<Command> {
+        self.requests.try_iter().collect()
+    }
+
+    /// Update the properties and emit `PropertiesChanged` / `StateChanged` for what changed.
+    pub(crate) fn publish(&mut self, status: &Status) {
+        if &self.published == status {
+            return;
+        }
+        if let Err(err) = self.emit_changes(status) {
+            eprintln!("Failed to publish state on D-Bus: {err}");
+        }
+        self.published = status.clone();
+    }
+
+    fn emit_changes(&self, status: &Status) -> zbus::Result<()> {
+        self.connection
+            .object_server()
+            .interface::<_, Service>(DBUS_PATH)?
+            .get_mut()
+            .status = status.clone();
+
+        let old = &self.published;
+        let mut changed: HashMap<&str, Value> = HashMap::new();
+        if old.active != status.active {
+            changed.insert("Active", status.active.into());
+        }
+        if old.mode != status.mode {
+            changed.insert("Mode", status.mode.as_str().into());
+        }
+        if old.bindings != status.bindings {
+            changed.insert("Keys", status.bindings.clone().into());
+        }
+        if old.locked != status.locked {
+            changed.insert("Locked", status.locked.into());
+        }
+        if changed.is_empty() {
+            return Ok(());
+        }
+        self.connection.emit_signal(
+            None::<&str>,
+            DBUS_PATH,
+            "org.freedesktop.DBus.Properties",
+            "PropertiesChanged",
+            &(DBUS_NAME, changed, Vec::<&str>::new()),
+        )?;
+        if old.active != status.active || old.locked != status.locked {
+            self.connection.emit_signal(
+                None::<&str>,
+                DBUS_PATH,
+                DBUS_NAME,
+                "StateChanged",
+                &(status.active, status.locked),
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Selects input devices by name, USB id, phys path or uniq instead of `/dev/input/eventN`.
+
+use evdev::Device;
+
+use crate::config::DeviceMatch;
+
+/// Match `text` against a pattern where `*` is any run of characters and `?` one character.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it was tried at.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Check a device against the `[device]` block.
+///
+/// Returns the criteria that matched, or why the device was rejected.
+pub(crate) fn match_device(filter: &DeviceMatch, device: &Device) -> Result<Vec<String>, String> {
+    let mut matched = Vec::new();
+    if let Some(pattern) = &filter.name {
+        let name = device.name().unwrap_or("");
+        if !glob_match(pattern, name) {
+            return Err(format!("name '{name}' does not match '{pattern}'"));
+        }
+        matched.push(format!("name '{name}'"));
+    }
+    if let Some((vendor, product)) = filter.id {
+        let id = device.input_id();
+        if (id.vendor(), id.product()) != (vendor, product) {
+            return Err(format!(
+                "id {:04x}:{:04x} is not {vendor:04x}:{product:04x}",
+                id.vendor(),
+                id.product()
+            ));
+        }
+        matched.push(format!("id {vendor:04x}:{product:04x}"));
+    }
+    if let Some(phys) = &filter.phys {
+        let actual = device.physical_path().unwrap_or("");
+        if actual != phys {
+            return Err(format!("phys '{actual}' is not '{phys}'"));
+        }
+        matched.push(format!("phys '{phys}'"));
+    }
+    if let Some(uniq) = &filter.uniq {
+        let actual = device.unique_name().unwrap_or("");
+        if actual != uniq {
+            return Err(format!("uniq '{actual}' is not '{uniq}'"));
+        }
+        matched.push(format!("uniq '{uniq}'"));
+    }
+    Ok(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_literal_names() {
+        assert!(glob_match("Kensington Slimblade", "Kensington Slimblade"));
+        assert!(!glob_match(
+            "Kensington Slimblade",
+            "Kensington Slimblade Pro"
+        ));
+        assert!(!glob_match("Kensington", "kensington"));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn star_matches_any_run() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("Logitech*", "Logitech G502"));
+        assert!(glob_match("*Keyboard", "AT Translated Set 2 Keyboard"));
+        assert!(glob_match("*G502*", "Logitech G502 HERO Gaming Mouse"));
+        assert!(glob_match("a**b", "ab"));
+        assert!(!glob_match("Logitech*", "Razer Logitech"));
+        assert!(!glob_match("*Mouse", "Mouse Keyboard"));
+    }
+
+    #[test]
+    fn star_backtracks_over_repeated_text() {
+        assert!(glob_match("*ab", "aab"));
+        assert!(glob_match("*a*b*c", "xaybzbc"));
+        assert!(glob_match(
+            "*Consumer Control",
+            "Consumer Control Consumer Control"
+        ));
+        assert!(!glob_match("*ab", "aba"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(glob_match("event?", "event7"));
+        assert!(!glob_match("event?", "event"));
+        assert!(!glob_match("event?", "event12"));
+        assert!(glob_match("Pedal ??", "Pedal ÄÖ"));
+    }
+}
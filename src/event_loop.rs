@@ -0,0 +1,86 @@
+//! Blocking wait on input devices, watchers and wake-ups so the main loop idles at zero CPU.
+
+use anyhow::{bail, Result};
+use std::io::ErrorKind;
+use std::mem::size_of;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::Duration;
+
+/// An eventfd other threads (e.g. the signal handler) write to to wake the main loop.
+pub(crate) struct Waker {
+    fd: OwnedFd,
+}
+
+impl Waker {
+    pub(crate) fn new() -> Result<Self> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+        if fd < 0 {
+            bail!(
+                "Failed to create eventfd: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    pub(crate) fn wake(&self) {
+        let value: u64 = 1;
+        unsafe {
+            libc::write(
+                self.fd.as_raw_fd(),
+                (&value as *const u64).cast(),
+                size_of::<u64>(),
+            );
+        }
+    }
+
+    /// Reset the counter after the main loop woke up.
+    pub(crate) fn drain(&self) {
+        let mut value: u64 = 0;
+        unsafe {
+            libc::read(
+                self.fd.as_raw_fd(),
+                (&mut value as *mut u64).cast(),
+                size_of::<u64>(),
+            );
+        }
+    }
+}
+
+impl AsRawFd for Waker {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// Block until one of `fds` is readable or `timeout` elapses (`None` = no timeout).
+pub(crate) fn wait(fds: &[RawFd], timeout: Option<Duration>) -> Result<()> {
+    let mut pollfds: Vec<libc::pollfd> = fds
+        .iter()
+        .map(|fd| libc::pollfd {
+            fd: *fd,
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+    // Round up so a pending timer is never polled with a zero timeout just before it is due.
+    let timeout_ms = timeout
+        .map(|t| t.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32)
+        .unwrap_or(-1);
+    let res = unsafe {
+        libc::poll(
+            pollfds.as_mut_ptr(),
+            pollfds.len() as libc::nfds_t,
+            timeout_ms,
+        )
+    };
+    if res < 0 {
+        let err = std::io::Error::last_os_error();
+        if err.kind() != ErrorKind::Interrupted {
+            bail!("Failed to poll input devices: {err}");
+        }
+    }
+    Ok(())
+}
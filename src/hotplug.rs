@@ -0,0 +1,49 @@
+//! Watches `/dev/input` with inotify so new input devices are picked up at once.
+
+use anyhow::Result;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+
+use crate::inotify::Inotify;
+
+const INPUT_DIR: &str = "/dev/input";
+/// Symlink directories that configured `device_path`s commonly point into.
+const LINK_DIRS: [&str; 2] = ["/dev/input/by-id", "/dev/input/by-path"];
+/// udev fixes up permissions after creating the node, hence IN_ATTRIB.
+const WATCH_MASK: u32 = libc::IN_CREATE | libc::IN_ATTRIB;
+
+pub(crate) struct DeviceWatcher {
+    inotify: Inotify,
+    /// Watch descriptor of `INPUT_DIR` itself.
+    input_wd: i32,
+}
+
+impl DeviceWatcher {
+    pub(crate) fn new() -> Result<Self> {
+        let inotify = Inotify::new()?;
+        let input_wd = inotify.add_watch(Path::new(INPUT_DIR), WATCH_MASK)?;
+        // Optional: only there once udev has created them.
+        for dir in LINK_DIRS {
+            let _ = inotify.add_watch(Path::new(dir), WATCH_MASK);
+        }
+        Ok(Self { inotify, input_wd })
+    }
+
+    /// Drain pending inotify events and report whether an `eventN` node appeared or changed.
+    pub(crate) fn changed(&mut self) -> bool {
+        let mut changed = false;
+        let input_wd = self.input_wd;
+        self.inotify.read_events(|wd, name| {
+            if wd != input_wd || name.starts_with(b"event") {
+                changed = true;
+            }
+        });
+        changed
+    }
+}
+
+impl AsRawFd for DeviceWatcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inotify.as_raw_fd()
+    }
+}
@@ -0,0 +1,73 @@
+//! Minimal inotify wrapper used to watch `/dev/input` and the config directory.
+
+use anyhow::{bail, Result};
+use std::ffi::CString;
+use std::mem::size_of;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::Path;
+
+pub(crate) struct Inotify {
+    fd: OwnedFd,
+}
+
+impl Inotify {
+    pub(crate) fn new() -> Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            bail!(
+                "Failed to initialize inotify: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    /// Watch a directory and return its watch descriptor.
+    pub(crate) fn add_watch(&self, dir: &Path, mask: u32) -> Result<i32> {
+        let path = CString::new(dir.as_os_str().as_bytes())?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), path.as_ptr(), mask) };
+        if wd < 0 {
+            bail!(
+                "Failed to watch {}: {}",
+                dir.display(),
+                std::io::Error::last_os_error()
+            );
+        }
+        Ok(wd)
+    }
+
+    /// Drain pending events, calling `f` with the watch descriptor and file name of each.
+    pub(crate) fn read_events(&mut self, mut f: impl FnMut(i32, &[u8])) {
+        let mut buf = [0u8; 4096];
+        loop {
+            let len =
+                unsafe { libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+            if len <= 0 {
+                return;
+            }
+            let len = len as usize;
+            let mut offset = 0;
+            while offset + size_of::<libc::inotify_event>() <= len {
+                let event = unsafe {
+                    std::ptr::read_unaligned(buf[offset..].as_ptr().cast::<libc::inotify_event>())
+                };
+                let name_start = offset + size_of::<libc::inotify_event>();
+                let name_end = (name_start + event.len as usize).min(len);
+                // The name is NUL-padded to the record length.
+                let name = &buf[name_start..name_end];
+                let name = &name[..name.iter().position(|b| *b == 0).unwrap_or(name.len())];
+                f(event.wd, name);
+                offset = name_end;
+            }
+        }
+    }
+}
+
+impl AsRawFd for Inotify {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
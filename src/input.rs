@@ -0,0 +1,32 @@
+//! Helpers shared by the daemon and `pttkey learn` for reading evdev devices.
+
+use anyhow::{bail, Result};
+use evdev::Device;
+use std::os::unix::io::AsRawFd;
+
+/// Whether `device` is one of our own passthrough devices.
+pub(crate) fn is_passthrough_device(device: &Device) -> bool {
+    device
+        .name()
+        .map(|name| name.starts_with("pttkey: "))
+        .unwrap_or(false)
+}
+
+pub(crate) fn set_device_nonblocking(device: &Device) -> Result<()> {
+    let fd = device.as_raw_fd();
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags < 0 {
+        bail!(
+            "Failed to read device flags: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    let res = unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) };
+    if res < 0 {
+        bail!(
+            "Failed to set device non-blocking: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(())
+}
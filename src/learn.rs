@@ -0,0 +1,175 @@
+//! `pttkey learn`: press the wanted key chord and save it to the config.
+
+use anyhow::{bail, Result};
+use evdev::{Device, EventSummary, KeyCode};
+use std::collections::HashSet;
+use std::io::{BufRead, ErrorKind, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+
+use crate::client::reload_or_restart;
+use crate::config::{
+    backup_config_path, config_path, key_label, persisted_from_config, write_persisted_config,
+    Config, PersistedDeviceMatch,
+};
+use crate::event_loop::wait;
+use crate::input::{is_passthrough_device, set_device_nonblocking};
+
+/// A chord seen during learning: keys in press order and the devices they came from.
+struct Learned {
+    keys: Vec<KeyCode>,
+    devices: HashSet<PathBuf>,
+}
+
+/// Ask a yes/no question on stdin; an empty answer picks `default`.
+fn confirm(question: &str, default: bool) -> Result<bool> {
+    // Drop whatever the learned chord typed into the terminal.
+    unsafe {
+        libc::tcflush(libc::STDIN_FILENO, libc::TCIFLUSH);
+    }
+    print!("{question} [{}] ", if default { "Y/n" } else { "y/N" });
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(match answer.trim().to_ascii_lowercase().as_str() {
+        "" => default,
+        "y" | "yes" => true,
+        _ => false,
+    })
+}
+
+/// Open every readable input device.
+fn open_all_devices() -> Result<Vec<(PathBuf, Device)>> {
+    let mut devices = Vec::new();
+    for (path, device) in evdev::enumerate() {
+        if is_passthrough_device(&device) || device.supported_keys().is_none() {
+            continue;
+        }
+        set_device_nonblocking(&device)?;
+        devices.push((path, device));
+    }
+    if devices.is_empty() {
+        bail!(
+            "No readable input devices. Hint: add your user to the input group or add a udev rule."
+        );
+    }
+    Ok(devices)
+}
+
+/// Print key events until a chord has been pressed and fully released.
+///
+/// Devices that fail (e.g. were unplugged) are dropped from `devices`.
+fn learn_chord(devices: &mut Vec<(PathBuf, Device)>) -> Result<Learned> {
+    let mut held: HashSet<KeyCode> = HashSet::new();
+    let mut learned = Learned {
+        keys: Vec::new(),
+        devices: HashSet::new(),
+    };
+    loop {
+        if devices.is_empty() {
+            bail!("No input devices left");
+        }
+        let fds: Vec<_> = devices.iter().map(|(_, d)| d.as_raw_fd()).collect();
+        wait(&fds, None)?;
+        let mut index = 0;
+        while index < devices.len() {
+            let fetched = devices[index]
+                .1
+                .fetch_events()
+                .map(|events| events.collect::<Vec<_>>());
+            let events = match fetched {
+                Ok(events) => events,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    index += 1;
+                    continue;
+                }
+                Err(err) => {
+                    let (path, _) = devices.remove(index);
+                    eprintln!("Input device {} lost: {err}", path.display());
+                    continue;
+                }
+            };
+            let (path, device) = &devices[index];
+            let name = device.name().unwrap_or("unknown");
+            for ev in events {
+                let EventSummary::Key(_, key, value) = ev.destructure() else {
+                    continue;
+                };
+                match value {
+                    1 => {
+                        println!(
+                            "{name} ({}): {} (code {}) pressed",
+                            path.display(),
+                            key_label(key),
+                            key.code()
+                        );
+                        held.insert(key);
+                        if !learned.keys.contains(&key) {
+                            learned.keys.push(key);
+                        }
+                        learned.devices.insert(path.clone());
+                    }
+                    // Releases of keys held before learning started (e.g. Enter) are ignored.
+                    0 if held.remove(&key) => {
+                        println!("{name} ({}): {} released", path.display(), key_label(key));
+                    }
+                    _ => {}
+                }
+            }
+            index += 1;
+        }
+        if held.is_empty() && !learned.keys.is_empty() {
+            return Ok(learned);
+        }
+    }
+}
+
+/// Run `pttkey learn` and write the learned chord into the persisted config.
+pub(crate) fn run(config: &Config) -> Result<()> {
+    let mut devices = open_all_devices()?;
+    println!("Press and release the key chord to use for push-to-talk (Ctrl+C to cancel).");
+    let learned = learn_chord(&mut devices)?;
+    let keys = learned
+        .keys
+        .iter()
+        .map(|key| key_label(*key))
+        .collect::<Vec<_>>();
+    println!("Learned keys: {}", keys.join("+"));
+    if !confirm("Save these keys?", true)? {
+        println!("Nothing saved");
+        return Ok(());
+    }
+
+    let mut persisted = persisted_from_config(config);
+    // The learned chord becomes the primary binding; extra [[bindings]] are kept.
+    persisted.keys = keys;
+    persisted.axis = None;
+    persisted.axis_press = None;
+    persisted.axis_release = None;
+    persisted.switch = None;
+    // Only a chord pressed on a single device (still plugged in) can be tied to it.
+    let single = match learned.devices.iter().collect::<Vec<_>>()[..] {
+        [path] => devices.iter().find(|(p, _)| p == path),
+        _ => None,
+    };
+    if let Some((_, device)) = single {
+        let name = device.name().unwrap_or("unknown");
+        let id = device.input_id();
+        let id = format!("{:04x}:{:04x}", id.vendor(), id.product());
+        if confirm(&format!("Only use the device '{name}' ({id})?"), false)? {
+            persisted.device_path = None;
+            persisted.device = Some(PersistedDeviceMatch {
+                name: Some(name.to_string()),
+                id: Some(id),
+                ..PersistedDeviceMatch::default()
+            });
+        }
+    }
+
+    let primary = config_path()?;
+    let backup = backup_config_path()?;
+    write_persisted_config(&persisted, &primary, &backup)?;
+    println!("Config saved");
+    reload_or_restart();
+    Ok(())
+}
@@ -1,27 +1,64 @@
 //! Push-to-talk mic control for PipeWire using evdev input devices.
 
 mod audio;
+mod axis;
+mod backend;
+mod client;
 mod config;
 mod constants;
+mod control;
+mod dbus;
+mod device_match;
+mod event_loop;
+mod hotplug;
+mod inotify;
+mod input;
+mod learn;
+mod notify;
+mod trigger;
+mod watch;
 
 use anyhow::{bail, Context, Result};
 use evdev::uinput::VirtualDevice;
-use evdev::{Device, EventSummary, InputEvent, KeyCode, SynchronizationCode, UinputAbsSetup};
-use std::collections::HashSet;
+use evdev::{
+    AbsoluteAxisCode, Device, EventSummary, InputEvent, KeyCode, SwitchCode, SynchronizationCode,
+    UinputAbsSetup,
+};
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs;
 use std::io::ErrorKind;
-use std::os::unix::io::AsRawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::audio::{apply_off, apply_on, init_audio_cache, play_transition_sound};
+use crate::audio::{
+    apply_off, apply_on, attach_new_sources, init_audio_cache, play_transition_sound, reconcile,
+    restore, snapshot, Snapshot,
+};
+use crate::axis::{supports_axis, AxisState};
+use crate::backend::{
+    check_backend, has_pending_sources, init_backend, print_sources, refresh_sources,
+    SOURCE_RETRY_INTERVAL,
+};
 use crate::config::{
-    backup_config_path, config_from_persisted, config_path, load_persisted_config, parse_args,
-    persisted_from_config, print_config, print_persisted_config, print_supported_keys,
-    read_persisted_config, restart_service, write_persisted_config, Config, StartupState,
+    action_label, backup_config_path, binding_label, config_from_persisted, config_path,
+    load_persisted_config, mode_label, parse_args, persisted_from_config, print_config,
+    print_persisted_config, print_supported_keys, read_persisted_config, trigger_label,
+    write_persisted_config, Action, Binding, Config, StartupState, Trigger,
 };
+use crate::control::{Command, ControlServer, Status};
+use crate::dbus::DbusService;
+use crate::device_match::match_device;
+use crate::event_loop::{wait, Waker};
+use crate::hotplug::DeviceWatcher;
+use crate::inotify::Inotify;
+use crate::input::{is_passthrough_device, set_device_nonblocking};
+use crate::notify::{notify, Category};
+use crate::trigger::{startup_state, TriggerState};
 
 fn print_devices() -> Result<()> {
     for (path, device) in evdev::enumerate() {
@@ -31,74 +68,216 @@ fn print_devices() -> Result<()> {
     Ok(())
 }
 
-fn is_passthrough_device(device: &Device) -> bool {
+/// An opened input device with its own key state and passthrough device.
+struct InputDevice {
+    path: PathBuf,
+    device: Device,
+    /// Held keys and when they went down.
+    pressed: HashMap<KeyCode, SystemTime>,
+    /// Ranges and pressed state of the axes used by bindings.
+    axes: AxisState,
+    /// Switches that are on.
+    switches: HashSet<SwitchCode>,
+    /// Held keys whose press was kept from other apps.
+    suppressed: HashSet<KeyCode>,
+    virtual_device: Option<VirtualDevice>,
+}
+
+fn supports_key(device: &Device, key: KeyCode) -> bool {
     device
-        .name()
-        .map(|name| name.starts_with("pttkey: "))
-        .unwrap_or(false)
-}
-
-/// Open the input device, using an explicit path or by probing available devices.
-fn open_device(config: &Config) -> Result<Device> {
-    if let Some(path) = &config.device_path {
-        let device = Device::open(path)
-            .with_context(|| format!("Failed to open device {}", path.display()))?;
-        if let Some(keys) = device.supported_keys() {
-            for key in &config.keys {
-                if !keys.contains(*key) {
-                    bail!(
-                        "Device {} does not support key {}",
-                        path.display(),
-                        key.code()
-                    );
-                }
+        .supported_keys()
+        .map(|keys| keys.contains(key))
+        .unwrap_or(true)
+}
+
+fn supports_switch(device: &Device, switch: SwitchCode) -> bool {
+    device
+        .supported_switches()
+        .is_some_and(|switches| switches.contains(switch))
+}
+
+/// Whether the device supports every input of at least one binding.
+fn supports_binding(config: &Config, device: &Device) -> bool {
+    config.bindings.iter().any(|binding| {
+        binding.keys.iter().all(|key| {
+            device
+                .supported_keys()
+                .is_some_and(|keys| keys.contains(*key))
+        }) && binding
+            .axis
+            .is_none_or(|trigger| supports_axis(device, trigger.axis))
+            && binding
+                .switch
+                .is_none_or(|switch| supports_switch(device, switch))
+    })
+}
+
+/// Whether the axis is used by any binding.
+fn is_bound_axis(config: &Config, axis: AbsoluteAxisCode) -> bool {
+    config
+        .bindings
+        .iter()
+        .any(|binding| binding.axis.is_some_and(|trigger| trigger.axis == axis))
+}
+
+/// Whether the switch is used by any binding.
+fn is_bound_switch(config: &Config, switch: SwitchCode) -> bool {
+    config
+        .bindings
+        .iter()
+        .any(|binding| binding.switch == Some(switch))
+}
+
+/// Whether an auto-detected device qualifies; returns why it matched or was rejected.
+fn check_candidate(config: &Config, device: &Device) -> Result<String, String> {
+    if !supports_binding(config, device) {
+        return Err("does not support the inputs of any binding".to_string());
+    }
+    let matched = match_device(&config.device_match, device)?;
+    if matched.is_empty() {
+        return Ok("supports the inputs of a binding".to_string());
+    }
+    Ok(format!("matched {}", matched.join(", ")))
+}
+
+/// Open the input devices, using explicit paths or every device that supports the keys.
+/// Reports which devices matched and why the other candidates were rejected.
+fn open_devices(config: &Config) -> Result<Vec<(PathBuf, Device)>> {
+    if !config.device_paths.is_empty() {
+        let mut devices = Vec::with_capacity(config.device_paths.len());
+        for path in &config.device_paths {
+            let device = Device::open(path)
+                .with_context(|| format!("Failed to open device {}", path.display()))?;
+            if let Err(reason) = match_device(&config.device_match, &device) {
+                bail!(
+                    "Device {} does not match [device]: {reason}",
+                    path.display()
+                );
+            }
+            devices.push((path.clone(), device));
+        }
+        // Keys of a chord may be spread over several devices.
+        for key in config.bindings.iter().flat_map(|binding| &binding.keys) {
+            if !devices.iter().any(|(_, device)| supports_key(device, *key)) {
+                let paths = devices
+                    .iter()
+                    .map(|(path, _)| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                bail!("Device {} does not support key {}", paths, key.code());
+            }
+        }
+        for trigger in config.bindings.iter().filter_map(|binding| binding.axis) {
+            if !devices
+                .iter()
+                .any(|(_, device)| supports_axis(device, trigger.axis))
+            {
+                bail!("No configured device supports axis {:?}", trigger.axis);
+            }
+        }
+        for switch in config.bindings.iter().filter_map(|binding| binding.switch) {
+            if !devices
+                .iter()
+                .any(|(_, device)| supports_switch(device, switch))
+            {
+                bail!("No configured device supports switch {switch:?}");
             }
         }
-        return Ok(device);
+        return Ok(devices);
     }
 
-    let mut devices: Vec<Device> = evdev::enumerate()
-        .map(|(_, d)| d)
-        .filter(|d| !is_passthrough_device(d))
-        .filter(|d| {
-            d.supported_keys()
-                .map(|k| config.keys.iter().all(|key| k.contains(*key)))
-                .unwrap_or(false)
-        })
-        .collect();
+    let mut devices = Vec::new();
+    for (path, device) in evdev::enumerate() {
+        if is_passthrough_device(&device) {
+            continue;
+        }
+        let label = format!(
+            "{} - {}",
+            path.display(),
+            device.name().unwrap_or("unknown")
+        );
+        match check_candidate(config, &device) {
+            Ok(reason) => {
+                println!("Matched {label}: {reason}");
+                devices.push((path, device));
+            }
+            Err(reason) => println!("Rejected {label}: {reason}"),
+        }
+    }
 
     if devices.is_empty() {
-        bail!("No input device found that supports all configured keys");
+        if config.device_match.is_empty() {
+            bail!("No input device found that supports the inputs of a binding");
+        }
+        bail!("No input device found that supports the inputs of a binding and matches [device]");
     }
 
-    Ok(devices.remove(0))
+    Ok(devices)
 }
 
 fn apply_startup_state(config: &Config) -> Result<()> {
-    match config.startup_state {
+    match startup_state(config) {
         StartupState::Muted => apply_off(config),
         StartupState::Unmuted => apply_on(config),
     }
 }
 
+/// Switch the mic and update `active` only once the backend accepted the change,
+/// so a failed transition is retried on the next event or reconcile tick.
 fn set_active_state(config: &Config, active: &mut bool, on: bool) -> Result<()> {
+    let applied = if on {
+        apply_on(config)
+    } else {
+        apply_off(config)
+    };
+    if let Err(err) = applied {
+        let summary = format!("Failed to turn mic {}", if on { "on" } else { "off" });
+        eprintln!("{summary}: {err:#}");
+        notify(config, Category::Backend, &summary, &format!("{err:#}"));
+        return Ok(());
+    }
     if on {
-        apply_on(config)?;
         play_transition_sound(config, true);
         println!("🎤 ON");
+        notify(config, Category::Mic, "Mic on", "");
     } else {
-        apply_off(config)?;
         play_transition_sound(config, false);
         println!("🔇 OFF");
+        notify(config, Category::Mic, "Mic muted", "");
     }
     *active = on;
     Ok(())
 }
 
-fn update_pressed_keys(pressed: &mut HashSet<KeyCode>, key: KeyCode, value: i32) {
+/// Capture the mic state before pttkey changes it, if restoring is enabled.
+fn take_snapshot(config: &Config) -> Option<Snapshot> {
+    config.restore_on_exit.then(|| snapshot(config))
+}
+
+/// Give up control of the mic: restore the startup snapshot or mute.
+fn release_mic(config: &Config, snapshot: Option<&Snapshot>) -> Result<()> {
+    match snapshot {
+        Some(snapshot) => {
+            restore(config, snapshot);
+            println!("↩ Mic state restored");
+        }
+        None => {
+            apply_off(config)?;
+            println!("🔇 Mic muted");
+        }
+    }
+    Ok(())
+}
+
+fn update_pressed_keys(
+    pressed: &mut HashMap<KeyCode, SystemTime>,
+    key: KeyCode,
+    value: i32,
+    time: SystemTime,
+) {
     match value {
         1 => {
-            pressed.insert(key);
+            pressed.insert(key, time);
         }
         0 => {
             pressed.remove(&key);
@@ -107,82 +286,169 @@ fn update_pressed_keys(pressed: &mut HashSet<KeyCode>, key: KeyCode, value: i32)
     }
 }
 
+/// Whether every input of the binding is held, honoring `exclusive` and `ordered`.
+fn binding_held(index: usize, binding: &Binding, devices: &[InputDevice]) -> bool {
+    if binding.axis.is_some() && !devices.iter().any(|d| d.axes.is_engaged(index)) {
+        return false;
+    }
+    if let Some(switch) = binding.switch {
+        if !devices.iter().any(|d| d.switches.contains(&switch)) {
+            return false;
+        }
+    }
+    // Keys held on different devices count together.
+    let pressed_at = |key: &KeyCode| devices.iter().find_map(|d| d.pressed.get(key).copied());
+    let Some(times) = binding
+        .keys
+        .iter()
+        .map(pressed_at)
+        .collect::<Option<Vec<_>>>()
+    else {
+        return false;
+    };
+    if binding.exclusive
+        && devices
+            .iter()
+            .flat_map(|d| d.pressed.keys())
+            .any(|key| !binding.keys.contains(key))
+    {
+        return false;
+    }
+    // The last key of an ordered binding has to go down after all the others.
+    if let (true, Some((last, modifiers))) = (binding.ordered, times.split_last()) {
+        if modifiers.iter().any(|time| time > last) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Whether a key event is kept from other apps, for `suppress`.
+///
+/// A press is suppressed when the key belongs to a plain binding, or when it completes an
+/// `exclusive` or `ordered` binding; the keys pressed before that have already reached other
+/// apps. Repeats and the release follow the press.
+fn suppress_key_event(
+    config: &Config,
+    devices: &mut [InputDevice],
+    index: usize,
+    key: KeyCode,
+    value: i32,
+) -> bool {
+    if value != 1 {
+        let suppressed = devices[index].suppressed.contains(&key);
+        if value == 0 {
+            devices[index].suppressed.remove(&key);
+        }
+        return suppressed;
+    }
+    let suppress = config.bindings.iter().enumerate().any(|(i, binding)| {
+        binding.keys.contains(&key)
+            && (!(binding.exclusive || binding.ordered) || binding_held(i, binding, devices))
+    });
+    if suppress {
+        devices[index].suppressed.insert(key);
+    }
+    suppress
+}
+
 fn refresh_active_state(
     config: &Config,
-    pressed: &HashSet<KeyCode>,
+    devices: &[InputDevice],
+    trigger: &mut TriggerState,
     active: &mut bool,
 ) -> Result<()> {
-    let all_pressed = config.keys.iter().all(|k| pressed.contains(k));
-    let desired_on = if config.reverse {
-        !all_pressed
-    } else {
-        all_pressed
-    };
+    let held: Vec<Action> = config
+        .bindings
+        .iter()
+        .enumerate()
+        .filter(|(index, binding)| binding_held(*index, binding, devices))
+        .map(|(_, binding)| binding.action)
+        .collect();
+    let desired_on = trigger.update(config, &held);
     if desired_on != *active {
         set_active_state(config, active, desired_on)?;
     }
     Ok(())
 }
 
+/// Process pending events of every device; returns the devices that failed.
 fn handle_events(
     config: &Config,
-    device: &mut Device,
-    pressed: &mut HashSet<KeyCode>,
+    devices: &mut [InputDevice],
+    trigger: &mut TriggerState,
     active: &mut bool,
-    virtual_device: &mut Option<VirtualDevice>,
-) -> Result<Option<std::io::Error>> {
-    let fetch_error = match device.fetch_events() {
-        Ok(events) => {
-            let mut forward_buffer: Vec<InputEvent> = Vec::new();
-            for ev in events {
-                let summary = ev.destructure();
-                if let EventSummary::Key(_, key, value) = summary {
-                    update_pressed_keys(pressed, key, value);
-                    refresh_active_state(config, pressed, active)?;
+) -> Result<Vec<(usize, std::io::Error)>> {
+    let mut lost = Vec::new();
+    for index in 0..devices.len() {
+        let events: Vec<InputEvent> = match devices[index].device.fetch_events() {
+            Ok(events) => events.collect(),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => continue,
+            Err(err) => {
+                lost.push((index, err));
+                continue;
+            }
+        };
+        let mut forward_buffer: Vec<InputEvent> = Vec::new();
+        for ev in events {
+            let summary = ev.destructure();
+            let mut suppress_key = false;
+            if let EventSummary::Key(_, key, value) = summary {
+                update_pressed_keys(&mut devices[index].pressed, key, value, ev.timestamp());
+                suppress_key =
+                    config.suppress && suppress_key_event(config, devices, index, key, value);
+                refresh_active_state(config, devices, trigger, active)?;
+            }
+            if let EventSummary::Switch(_, switch, value) = summary {
+                if value != 0 {
+                    devices[index].switches.insert(switch);
+                } else {
+                    devices[index].switches.remove(&switch);
                 }
-                if let Some(virtual_device) = virtual_device.as_mut() {
-                    match summary {
-                        EventSummary::Key(_, key, _)
-                            if config.suppress && config.keys.contains(&key) => {}
-                        EventSummary::Synchronization(_, code, _)
-                            if code == SynchronizationCode::SYN_REPORT =>
-                        {
-                            if !forward_buffer.is_empty() {
-                                let _ = virtual_device.emit(&forward_buffer);
-                                forward_buffer.clear();
-                            }
-                        }
-                        _ => {
-                            forward_buffer.push(ev);
-                        }
-                    }
+                refresh_active_state(config, devices, trigger, active)?;
+            }
+            if let EventSummary::AbsoluteAxis(_, axis, value) = summary {
+                if devices[index].axes.update(config, axis, value) {
+                    refresh_active_state(config, devices, trigger, active)?;
                 }
             }
-            if let Some(virtual_device) = virtual_device.as_mut() {
-                if !forward_buffer.is_empty() {
-                    let _ = virtual_device.emit(&forward_buffer);
+            if let Some(virtual_device) = devices[index].virtual_device.as_mut() {
+                match summary {
+                    EventSummary::Key(..) if suppress_key => {}
+                    EventSummary::AbsoluteAxis(_, axis, _)
+                        if config.suppress && is_bound_axis(config, axis) => {}
+                    EventSummary::Switch(_, switch, _)
+                        if config.suppress && is_bound_switch(config, switch) => {}
+                    EventSummary::Synchronization(_, code, _)
+                        if code == SynchronizationCode::SYN_REPORT =>
+                    {
+                        if !forward_buffer.is_empty() {
+                            let _ = virtual_device.emit(&forward_buffer);
+                            forward_buffer.clear();
+                        }
+                    }
+                    _ => {
+                        forward_buffer.push(ev);
+                    }
                 }
             }
-            None
         }
-        Err(err) => {
-            if err.kind() == ErrorKind::WouldBlock {
-                None
-            } else {
-                Some(err)
+        if let Some(virtual_device) = devices[index].virtual_device.as_mut() {
+            if !forward_buffer.is_empty() {
+                let _ = virtual_device.emit(&forward_buffer);
             }
         }
-    };
-    Ok(fetch_error)
+    }
+    Ok(lost)
 }
 
 fn is_permission_denied(err: &anyhow::Error) -> bool {
     err.to_string().contains("Permission denied")
 }
 
-fn open_device_with_hint(config: &Config) -> Result<Device> {
-    match open_device(config) {
-        Ok(device) => Ok(device),
+fn open_devices_with_hint(config: &Config) -> Result<Vec<(PathBuf, Device)>> {
+    match open_devices(config) {
+        Ok(devices) => Ok(devices),
         Err(err) => {
             if is_permission_denied(&err) {
                 eprintln!("Hint: add your user to the input group or add a udev rule.");
@@ -192,44 +458,65 @@ fn open_device_with_hint(config: &Config) -> Result<Device> {
     }
 }
 
-fn reopen_device_loop(config: &Config) -> Result<Device> {
-    loop {
-        match open_device_nonblocking(config) {
-            Ok(reopened) => return Ok(reopened),
-            Err(open_err) => {
-                if is_permission_denied(&open_err) {
-                    return Err(open_err);
-                }
-                eprintln!("Retrying device open: {open_err}");
-                std::thread::sleep(Duration::from_secs(1));
-            }
-        }
-    }
+/// Prepare an opened device for the event loop: non-blocking, with suppression applied.
+fn add_input_device(config: &Config, path: PathBuf, mut device: Device) -> Result<InputDevice> {
+    set_device_nonblocking(&device)?;
+    let virtual_device = apply_device_suppression(config, &mut device)?;
+    println!(
+        "Input device: {} - {}",
+        path.display(),
+        device.name().unwrap_or("unknown")
+    );
+    let axes = AxisState::new(config, &device);
+    // A switch that is already on (e.g. a mute slider) counts from the start.
+    let switches = device
+        .get_switch_state()
+        .map(|state| state.iter().collect())
+        .unwrap_or_default();
+    Ok(InputDevice {
+        path,
+        device,
+        pressed: HashMap::new(),
+        axes,
+        switches,
+        suppressed: HashSet::new(),
+        virtual_device,
+    })
 }
 
-fn set_device_nonblocking(device: &Device) -> Result<()> {
-    let fd = device.as_raw_fd();
-    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
-    if flags < 0 {
-        bail!(
-            "Failed to read device flags: {}",
-            std::io::Error::last_os_error()
-        );
-    }
-    let res = unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) };
-    if res < 0 {
-        bail!(
-            "Failed to set device non-blocking: {}",
-            std::io::Error::last_os_error()
-        );
-    }
-    Ok(())
+/// Open every configured device, failing if any of them is unusable.
+fn open_input_devices(config: &Config) -> Result<Vec<InputDevice>> {
+    open_devices_with_hint(config)?
+        .into_iter()
+        .map(|(path, device)| add_input_device(config, path, device))
+        .collect()
 }
 
-fn open_device_nonblocking(config: &Config) -> Result<Device> {
-    let device = open_device_with_hint(config)?;
-    set_device_nonblocking(&device)?;
-    Ok(device)
+/// Open matching devices that are not in use yet, e.g. after a hotplug event.
+fn attach_new_devices(config: &Config, devices: &mut Vec<InputDevice>) {
+    let known: HashSet<PathBuf> = devices.iter().map(|d| d.path.clone()).collect();
+    let candidates: Vec<(PathBuf, Device)> = if config.device_paths.is_empty() {
+        evdev::enumerate()
+            .filter(|(path, _)| !known.contains(path))
+            .filter(|(_, d)| !is_passthrough_device(d))
+            .filter(|(_, d)| check_candidate(config, d).is_ok())
+            .collect()
+    } else {
+        config
+            .device_paths
+            .iter()
+            .filter(|path| !known.contains(*path))
+            // Not there yet or udev has not fixed the permissions; retried on the next event.
+            .filter_map(|path| Device::open(path).ok().map(|d| (path.clone(), d)))
+            .filter(|(_, d)| match_device(&config.device_match, d).is_ok())
+            .collect()
+    };
+    for (path, device) in candidates {
+        match add_input_device(config, path, device) {
+            Ok(input) => devices.push(input),
+            Err(err) => eprintln!("{err:#}"),
+        }
+    }
 }
 
 fn create_virtual_device(device: &Device) -> Result<VirtualDevice> {
@@ -298,45 +585,134 @@ fn config_mtime(path: &std::path::Path) -> Option<std::time::SystemTime> {
     fs::metadata(path).and_then(|meta| meta.modified()).ok()
 }
 
-fn spawn_config_watcher(
-    config_path: std::path::PathBuf,
-    running: Arc<AtomicBool>,
-) -> mpsc::Receiver<Config> {
-    let (tx, rx) = mpsc::channel();
-    std::thread::spawn(move || {
-        let mut last_modified = config_mtime(&config_path);
-        while running.load(Ordering::SeqCst) {
-            std::thread::sleep(Duration::from_millis(500));
-            let modified = config_mtime(&config_path);
-            if modified != last_modified {
-                last_modified = modified;
-                if modified.is_none() {
-                    continue;
-                }
-                match read_persisted_config(&config_path).and_then(config_from_persisted) {
-                    Ok(config) => {
-                        let _ = tx.send(config);
-                    }
-                    Err(err) => {
-                        eprintln!("Failed to reload config: {err}");
-                    }
-                }
+/// Reloads the config when its file is written or replaced (editors often rename over it).
+struct ConfigWatcher {
+    inotify: Inotify,
+    path: PathBuf,
+    last_modified: Option<std::time::SystemTime>,
+}
+
+impl ConfigWatcher {
+    fn new(path: PathBuf) -> Result<Self> {
+        let inotify = Inotify::new()?;
+        let dir = path
+            .parent()
+            .context("Config path has no parent directory")?;
+        inotify.add_watch(
+            dir,
+            libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE | libc::IN_DELETE,
+        )?;
+        let last_modified = config_mtime(&path);
+        Ok(Self {
+            inotify,
+            path,
+            last_modified,
+        })
+    }
+
+    /// Return the new config if the file changed since the last call.
+    ///
+    /// Errors are reported with the notification settings of `current`.
+    fn poll(&mut self, current: &Config) -> Option<Config> {
+        let file_name = self.path.file_name()?.as_bytes().to_vec();
+        let mut touched = false;
+        self.inotify
+            .read_events(|_, name| touched |= name == file_name);
+        if !touched {
+            return None;
+        }
+        let modified = config_mtime(&self.path);
+        if modified == self.last_modified {
+            return None;
+        }
+        self.last_modified = modified;
+        // Deleted: keep running with the current config.
+        modified?;
+        match self.load() {
+            Ok(config) => Some(config),
+            Err(err) => {
+                eprintln!("Failed to reload config: {err}");
+                notify(
+                    current,
+                    Category::Config,
+                    "Failed to reload config",
+                    &format!("{err:#}"),
+                );
+                None
             }
         }
-    });
-    rx
+    }
+
+    fn load(&self) -> Result<Config> {
+        read_persisted_config(&self.path).and_then(config_from_persisted)
+    }
+}
+
+impl AsRawFd for ConfigWatcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inotify.as_raw_fd()
+    }
+}
+
+fn current_status(
+    config: &Config,
+    devices: &[InputDevice],
+    trigger: &TriggerState,
+    active: bool,
+) -> Status {
+    Status {
+        active,
+        locked: trigger.is_locked(),
+        trigger: trigger_label(config.trigger).to_string(),
+        mode: mode_label(config.mode).to_string(),
+        bindings: config.bindings.iter().map(binding_label).collect(),
+        devices: devices
+            .iter()
+            .map(|d| d.path.display().to_string())
+            .collect(),
+    }
+}
+
+/// Apply a control socket or D-Bus request; a reload leaves the new config in `requested_config`.
+fn handle_command(
+    command: &Command,
+    config: &Config,
+    active: bool,
+    trigger: &mut TriggerState,
+    config_watcher: &ConfigWatcher,
+    requested_config: &mut Option<Config>,
+) -> Result<(), String> {
+    match command {
+        Command::Status | Command::Subscribe => {}
+        Command::On => trigger.set_resting(config, true),
+        Command::Off => trigger.set_resting(config, false),
+        Command::Toggle => trigger.set_resting(config, !active),
+        Command::LockMuted => trigger.set_locked(true),
+        Command::Unlock => trigger.set_locked(false),
+        Command::Reload => {
+            *requested_config = Some(config_watcher.load().map_err(|err| format!("{err:#}"))?);
+        }
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| client::is_command(arg)) {
+        return client::run(&args);
+    }
     let (base_config, created, config_path_used) = load_persisted_config()?;
     print_persisted_config(&config_path_used, &base_config);
-    let (mut config, persist_changed) = parse_args(base_config)?;
+    let (mut config, persist_changed) = parse_args(base_config, &args)?;
+    if config.learn {
+        return learn::run(&config);
+    }
     if persist_changed {
         let persisted = persisted_from_config(&config);
         let primary = config_path()?;
         let backup = backup_config_path()?;
         write_persisted_config(&persisted, &primary, &backup)?;
-        restart_service();
+        client::reload_or_restart();
         return Ok(());
     }
     if created {
@@ -353,98 +729,397 @@ fn main() -> Result<()> {
     if config.list_devices {
         return print_devices();
     }
+    if config.list_sources {
+        return print_sources(&config);
+    }
     if config.print_config {
         print_config(&config);
         if config.dry_run {
-            let _ = open_device(&config)?;
+            let _ = open_devices(&config)?;
         }
         return Ok(());
     }
 
     if config.dry_run {
-        let _ = open_device(&config)?;
+        for (path, device) in open_devices(&config)? {
+            println!(
+                "Input device: {} - {}",
+                path.display(),
+                device.name().unwrap_or("unknown")
+            );
+        }
+        println!("Mic backend: {}", check_backend(&config)?);
         println!("Dry run OK");
         return Ok(());
     }
 
     init_audio_cache(&config)?;
+    init_backend(&config)?;
+    let mut snapshot = take_snapshot(&config);
 
     // Ensure mic is muted immediately on start
     apply_startup_state(&config)?;
-    match config.startup_state {
+    match startup_state(&config) {
         StartupState::Muted => println!("🔇 Mic muted on start"),
         StartupState::Unmuted => println!("🎤 Mic unmuted on start"),
     }
 
     // Ensure mic is muted on exit / crash
     let running = Arc::new(AtomicBool::new(true));
+    let waker = Arc::new(Waker::new()?);
     let r = running.clone();
+    let w = waker.clone();
 
     ctrlc::set_handler(move || {
         r.store(false, Ordering::SeqCst);
+        w.wake();
     })
     .expect("Failed to set Ctrl-C handler");
 
-    let config_updates = spawn_config_watcher(config_path_used, running.clone());
-    let mut device = open_device_nonblocking(&config)?;
-    let mut virtual_device = apply_device_suppression(&config, &mut device)?;
+    let mut config_watcher = ConfigWatcher::new(config_path_used)?;
+    let mut watcher = DeviceWatcher::new()?;
+    let mut devices = match open_input_devices(&config) {
+        Ok(devices) => devices,
+        Err(err) if is_permission_denied(&err) => return Err(err),
+        Err(err) => {
+            eprintln!("{err:#}");
+            // Keep whatever is there already, e.g. the listed device paths that do exist.
+            let mut devices = Vec::new();
+            attach_new_devices(&config, &mut devices);
+            devices
+        }
+    };
 
-    if config.reverse {
-        println!("🎙 Hold the configured button to mute");
-    } else {
-        println!("🎙 Hold the configured button to talk");
+    match config.trigger {
+        Trigger::Toggle => println!("🎙 Press the configured button to toggle the mic"),
+        Trigger::Hybrid => {
+            println!("🎙 Tap the configured button to latch the mic, hold it to talk")
+        }
+        Trigger::Hold if config.reverse => println!("🎙 Hold the configured button to mute"),
+        Trigger::Hold => println!("🎙 Hold the configured button to talk"),
+    }
+    for binding in &config.bindings {
+        if binding.action != Action::Trigger {
+            println!(
+                "🎙 {}: {}",
+                binding_label(binding),
+                action_label(binding.action)
+            );
+        }
     }
 
-    let mut pressed: HashSet<KeyCode> = HashSet::new();
-    let mut active = false;
+    let mut trigger = TriggerState::new(&config);
+    let mut active = matches!(startup_state(&config), StartupState::Unmuted);
+    let mut last_reconcile = Instant::now();
+    let mut last_source_check = Instant::now();
+    // The mic was handed back to its snapshot state because no device is left.
+    let mut restored = false;
+    if devices.is_empty() {
+        println!("Waiting for input device...");
+        trigger.reset();
+    }
+
+    refresh_active_state(&config, &devices, &mut trigger, &mut active)?;
 
-    refresh_active_state(&config, &pressed, &mut active)?;
+    let mut control = match ControlServer::new() {
+        Ok(server) => Some(server),
+        Err(err) => {
+            eprintln!("Control socket disabled: {err:#}");
+            None
+        }
+    };
+    let mut dbus = match DbusService::new(
+        current_status(&config, &devices, &trigger, active),
+        waker.clone(),
+    ) {
+        Ok(service) => Some(service),
+        Err(err) => {
+            eprintln!("D-Bus service disabled: {err:#}");
+            None
+        }
+    };
 
     while running.load(Ordering::SeqCst) {
-        if let Some(err) = handle_events(
-            &config,
-            &mut device,
-            &mut pressed,
-            &mut active,
-            &mut virtual_device,
-        )? {
-            eprintln!("Input device error: {err}. Reopening...");
-            apply_off(&config)?;
-            active = false;
-            pressed.clear();
-            device = reopen_device_loop(&config)?;
-            virtual_device = apply_device_suppression(&config, &mut device)?;
-        }
-
-        if let Ok(new_config) = config_updates.try_recv() {
-            let keys_changed = config.keys != new_config.keys;
-            let device_changed = config.device_path != new_config.device_path;
+        let reconcile_at = (config.reconcile_interval_ms > 0 && !restored)
+            .then(|| last_reconcile + Duration::from_millis(config.reconcile_interval_ms));
+        // Missing capture sources are polled for; there is no event when one appears.
+        let source_check_at =
+            (!restored && has_pending_sources()).then(|| last_source_check + SOURCE_RETRY_INTERVAL);
+        let next_timer = [trigger.deadline(&config), reconcile_at, source_check_at]
+            .into_iter()
+            .flatten()
+            .min();
+        let mut fds: Vec<RawFd> = devices.iter().map(|d| d.device.as_raw_fd()).collect();
+        fds.extend([
+            watcher.as_raw_fd(),
+            config_watcher.as_raw_fd(),
+            waker.as_raw_fd(),
+        ]);
+        if let Some(server) = &control {
+            fds.extend(server.fds());
+        }
+        wait(
+            &fds,
+            next_timer.map(|at| at.saturating_duration_since(Instant::now())),
+        )?;
+        waker.drain();
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let lost = handle_events(&config, &mut devices, &mut trigger, &mut active)?;
+        if !lost.is_empty() {
+            for (index, err) in lost.into_iter().rev() {
+                let input = devices.remove(index);
+                eprintln!("Input device {} lost: {err}", input.path.display());
+                notify(
+                    &config,
+                    Category::Device,
+                    "Input device lost",
+                    &format!(
+                        "{} ({})",
+                        input.device.name().unwrap_or("unknown"),
+                        input.path.display()
+                    ),
+                );
+            }
+            if devices.is_empty() {
+                println!("Waiting for input device...");
+                // A locked mic stays muted instead of going back to its snapshot state.
+                let locked = trigger.is_locked();
+                let snapshot = snapshot.as_ref().filter(|_| !locked);
+                if let Err(err) = release_mic(&config, snapshot) {
+                    eprintln!("Failed to release mic: {err:#}");
+                    notify(
+                        &config,
+                        Category::Backend,
+                        "Failed to release mic",
+                        &format!("{err:#}"),
+                    );
+                }
+                active = false;
+                trigger.reset();
+                restored = snapshot.is_some();
+            } else {
+                refresh_active_state(&config, &devices, &mut trigger, &mut active)?;
+            }
+        }
+
+        if watcher.changed() {
+            let count = devices.len();
+            attach_new_devices(&config, &mut devices);
+            if restored && !devices.is_empty() {
+                // Take the mic back from the restored snapshot state; retried on the next change
+                // if the backend fails.
+                match apply_off(&config) {
+                    Ok(()) => restored = false,
+                    Err(err) => {
+                        eprintln!("Failed to mute mic: {err:#}");
+                        notify(
+                            &config,
+                            Category::Backend,
+                            "Failed to mute mic",
+                            &format!("{err:#}"),
+                        );
+                    }
+                }
+            }
+            for input in &devices[count..] {
+                notify(
+                    &config,
+                    Category::Device,
+                    "Input device connected",
+                    &format!(
+                        "{} ({})",
+                        input.device.name().unwrap_or("unknown"),
+                        input.path.display()
+                    ),
+                );
+            }
+            if devices.len() > count {
+                // Switches and axes already on at the new device count at once.
+                refresh_active_state(&config, &devices, &mut trigger, &mut active)?;
+            }
+        }
+
+        if trigger
+            .deadline(&config)
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            refresh_active_state(&config, &devices, &mut trigger, &mut active)?;
+        }
+
+        // Replies are sent at the end of the iteration, with the resulting status.
+        let mut replies = Vec::new();
+        let mut requested_config = None;
+        let mut requested = false;
+        if let Some(server) = control.as_mut() {
+            for (id, request) in server.read_requests() {
+                let result = match request {
+                    Err(err) => Err(err),
+                    Ok(Command::Subscribe) => {
+                        server.subscribe(id);
+                        Ok(())
+                    }
+                    Ok(command) => handle_command(
+                        &command,
+                        &config,
+                        active,
+                        &mut trigger,
+                        &config_watcher,
+                        &mut requested_config,
+                    ),
+                };
+                replies.push((id, result));
+                requested = true;
+            }
+        }
+        if let Some(service) = &dbus {
+            for command in service.take_requests() {
+                if let Err(err) = handle_command(
+                    &command,
+                    &config,
+                    active,
+                    &mut trigger,
+                    &config_watcher,
+                    &mut requested_config,
+                ) {
+                    eprintln!("D-Bus request failed: {err}");
+                    notify(&config, Category::Config, "Failed to reload config", &err);
+                }
+                requested = true;
+            }
+        }
+        if requested {
+            refresh_active_state(&config, &devices, &mut trigger, &mut active)?;
+        }
+
+        let watched_config = config_watcher.poll(&config);
+        if let Some(new_config) = requested_config.or(watched_config) {
+            let keys_changed = config.bindings != new_config.bindings;
+            let device_changed = config.device_paths != new_config.device_paths
+                || config.device_match != new_config.device_match;
             let suppress_changed = config.suppress != new_config.suppress;
+            let trigger_changed = config.trigger != new_config.trigger;
+            let backend_changed = config.backend != new_config.backend
+                || config.alsa_card != new_config.alsa_card
+                || config.alsa_control != new_config.alsa_control;
+            let target_changed = backend_changed || config.targets != new_config.targets;
+            if target_changed && (active || snapshot.is_some()) {
+                // Do not leave the previous source live when switching away from it.
+                release_mic(&config, snapshot.as_ref())?;
+                active = false;
+            }
             config = new_config;
             if let Err(err) = init_audio_cache(&config) {
                 eprintln!("Failed to reload sounds: {err}");
+                notify(
+                    &config,
+                    Category::Config,
+                    "Failed to reload sounds",
+                    &err.to_string(),
+                );
+            }
+            if backend_changed {
+                if let Err(err) = init_backend(&config) {
+                    eprintln!("Failed to switch mic backend: {err}");
+                    notify(
+                        &config,
+                        Category::Backend,
+                        "Failed to switch mic backend",
+                        &err.to_string(),
+                    );
+                }
+            } else if let Err(err) = refresh_sources(&config) {
+                eprintln!("Failed to resolve mic sources: {err}");
+                notify(
+                    &config,
+                    Category::Backend,
+                    "Failed to resolve mic sources",
+                    &err.to_string(),
+                );
+            }
+            if target_changed || config.restore_on_exit != snapshot.is_some() {
+                snapshot = take_snapshot(&config);
+            }
+            if target_changed {
+                // A missing source is muted by the pending-source retry once it shows up.
+                match apply_off(&config) {
+                    Ok(()) => active = false,
+                    Err(err) => {
+                        eprintln!("Failed to mute the new mic source: {err:#}");
+                        notify(
+                            &config,
+                            Category::Backend,
+                            "Failed to mute the new mic source",
+                            &format!("{err:#}"),
+                        );
+                    }
+                }
             }
             if keys_changed || device_changed {
-                apply_off(&config)?;
-                active = false;
-                pressed.clear();
-                device = reopen_device_loop(&config)?;
-                virtual_device = apply_device_suppression(&config, &mut device)?;
+                match apply_off(&config) {
+                    Ok(()) => active = false,
+                    Err(err) => {
+                        eprintln!("Failed to mute mic: {err:#}");
+                        notify(
+                            &config,
+                            Category::Backend,
+                            "Failed to mute mic",
+                            &format!("{err:#}"),
+                        );
+                    }
+                }
+                trigger.reset();
+                // Release the grabs before probing the devices again.
+                devices.clear();
+                attach_new_devices(&config, &mut devices);
+                if devices.is_empty() {
+                    println!("Waiting for input device...");
+                }
+            }
+            if trigger_changed {
+                let locked = trigger.is_locked();
+                trigger = TriggerState::new(&config);
+                trigger.set_locked(locked);
             }
             if suppress_changed && !(keys_changed || device_changed) {
-                virtual_device = apply_device_suppression(&config, &mut device)?;
+                for input in &mut devices {
+                    input.virtual_device = apply_device_suppression(&config, &mut input.device)?;
+                }
             }
-            refresh_active_state(&config, &pressed, &mut active)?;
+            refresh_active_state(&config, &devices, &mut trigger, &mut active)?;
             println!("Config reloaded");
         }
 
-        let sleep_ms = if config.suppress { 1 } else { 10 };
-        std::thread::sleep(Duration::from_millis(sleep_ms));
+        if source_check_at.is_some_and(|at| Instant::now() >= at) {
+            last_source_check = Instant::now();
+            attach_new_sources(&config, active);
+        }
+
+        if config.reconcile_interval_ms > 0
+            && !restored
+            && last_reconcile.elapsed() >= Duration::from_millis(config.reconcile_interval_ms)
+        {
+            last_reconcile = Instant::now();
+            refresh_active_state(&config, &devices, &mut trigger, &mut active)?;
+            reconcile(&config, active);
+        }
+
+        let status = current_status(&config, &devices, &trigger, active);
+        if let Some(server) = control.as_mut() {
+            for (id, result) in replies {
+                server.reply(id, result.map(|()| &status));
+            }
+            server.publish(&status);
+        }
+        if let Some(service) = dbus.as_mut() {
+            service.publish(&status);
+        }
     }
 
-    // Final safety mute
-    apply_off(&config)?;
-    println!("🔇 Mic muted on exit");
+    // Final safety mute (or restore of the state found at startup)
+    release_mic(&config, snapshot.as_ref())?;
 
     Ok(())
 }
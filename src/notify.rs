@@ -0,0 +1,131 @@
+//! Desktop notifications over `org.freedesktop.Notifications`.
+//!
+//! Notifications are sent from a background thread so a slow or missing notification
+//! daemon never stalls the main loop. Each category replaces its own previous notification
+//! instead of stacking up.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Sender};
+use std::sync::OnceLock;
+use zbus::blocking::Connection;
+use zbus::zvariant::Value;
+
+use crate::config::{Config, NotifySetting, Urgency};
+
+/// Kind of event a notification is about; each has its own toggle and urgency.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Category {
+    Device,
+    Backend,
+    Config,
+    Mic,
+}
+
+struct Notification {
+    category: Category,
+    urgency: Urgency,
+    summary: String,
+    body: String,
+}
+
+static NOTIFY_SENDER: OnceLock<Sender<Notification>> = OnceLock::new();
+
+fn get_notify_sender() -> Option<&'static Sender<Notification>> {
+    if NOTIFY_SENDER.get().is_none() {
+        let (tx, rx) = mpsc::channel::<Notification>();
+        let _ = NOTIFY_SENDER.set(tx);
+        std::thread::spawn(move || {
+            let mut connection: Option<Connection> = None;
+            // Id of the notification last shown per category.
+            let mut shown: HashMap<Category, u32> = HashMap::new();
+            let mut warned = false;
+            for notification in rx {
+                if connection.is_none() {
+                    connection = Connection::session().ok();
+                }
+                let Some(conn) = &connection else {
+                    if !warned {
+                        eprintln!("Failed to send notification: no session bus");
+                        warned = true;
+                    }
+                    continue;
+                };
+                let replaces = shown.get(&notification.category).copied().unwrap_or(0);
+                match send(conn, &notification, replaces) {
+                    Ok(id) => {
+                        shown.insert(notification.category, id);
+                    }
+                    Err(err) if !warned => {
+                        eprintln!("Failed to send notification: {err}");
+                        warned = true;
+                    }
+                    Err(_) => {}
+                }
+            }
+        });
+    }
+    NOTIFY_SENDER.get()
+}
+
+fn send(connection: &Connection, notification: &Notification, replaces: u32) -> zbus::Result<u32> {
+    let urgency: u8 = match notification.urgency {
+        Urgency::Low => 0,
+        Urgency::Normal => 1,
+        Urgency::Critical => 2,
+    };
+    let mut hints: HashMap<&str, Value> = HashMap::new();
+    hints.insert("urgency", urgency.into());
+    // Mic transitions are an on-screen display, not something to keep in the history.
+    if notification.category == Category::Mic {
+        hints.insert("transient", true.into());
+    }
+    let icon = match notification.category {
+        Category::Device => "input-keyboard",
+        Category::Backend => "dialog-error",
+        Category::Config => "dialog-warning",
+        Category::Mic => "audio-input-microphone",
+    };
+    let reply = connection.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &(
+            "pttkey",
+            replaces,
+            icon,
+            notification.summary.as_str(),
+            notification.body.as_str(),
+            Vec::<&str>::new(),
+            hints,
+            -1i32,
+        ),
+    )?;
+    reply.body().deserialize()
+}
+
+fn setting(config: &Config, category: Category) -> NotifySetting {
+    let notifications = &config.notifications;
+    match category {
+        Category::Device => notifications.device,
+        Category::Backend => notifications.backend,
+        Category::Config => notifications.config,
+        Category::Mic => notifications.mic,
+    }
+}
+
+/// Show a notification if `category` is enabled in the config.
+pub(crate) fn notify(config: &Config, category: Category, summary: &str, body: &str) {
+    let setting = setting(config, category);
+    if !setting.enabled {
+        return;
+    }
+    if let Some(sender) = get_notify_sender() {
+        let _ = sender.send(Notification {
+            category,
+            urgency: setting.urgency,
+            summary: summary.to_string(),
+            body: body.to_string(),
+        });
+    }
+}
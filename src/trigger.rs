@@ -0,0 +1,409 @@
+//! Turns the held state of the configured bindings into the desired mic state.
+//!
+//! When several bindings are held at once, a mute lock wins over push-to-mute, which
+//! wins over push-to-talk, which wins over the `trigger` bindings and toggled state.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::config::{Action, Config, StartupState, Trigger};
+
+/// Trigger state that has to survive between input events.
+pub(crate) struct TriggerState {
+    /// When a binding of each held action went down (before `press_delay_ms` is applied).
+    held_since: HashMap<Action, Instant>,
+    /// Actions that counted as held at the last update.
+    down: HashSet<Action>,
+    /// Mic state latched by the last toggle press or hybrid tap; in hold mode it
+    /// flips the state while no key is held.
+    latched: bool,
+    /// Set by a mute-lock binding; the mic stays muted until it is pressed again.
+    locked: bool,
+    /// When the current hybrid press started and whether the mic was latched then.
+    press: Option<(Instant, bool)>,
+    /// Mic state returned by the last update.
+    on: bool,
+    /// End of the `release_delay_ms` tail while the mic is kept on.
+    tail_until: Option<Instant>,
+    /// Mic state from `startup_state` in hold mode, kept until a trigger binding is pressed.
+    startup: Option<bool>,
+}
+
+impl TriggerState {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            held_since: HashMap::new(),
+            down: HashSet::new(),
+            latched: config.trigger != Trigger::Hold
+                && matches!(config.toggle_startup_state, StartupState::Unmuted),
+            locked: false,
+            press: None,
+            on: false,
+            tail_until: None,
+            startup: (config.trigger == Trigger::Hold)
+                .then_some(matches!(config.startup_state, StartupState::Unmuted)),
+        }
+    }
+
+    /// Forget held keys and latch the mic off, e.g. after the device was lost. A mute lock
+    /// stays in place so the mic does not come back on with the device.
+    pub(crate) fn reset(&mut self) {
+        self.held_since.clear();
+        self.down.clear();
+        self.latched = false;
+        self.press = None;
+        self.on = false;
+        self.tail_until = None;
+        self.startup = None;
+    }
+
+    /// Set the state the mic rests in while no binding is held, e.g. from the control socket.
+    pub(crate) fn set_resting(&mut self, config: &Config, on: bool) {
+        self.startup = None;
+        self.latched = match config.trigger {
+            Trigger::Hold => on != config.reverse,
+            Trigger::Toggle | Trigger::Hybrid => on,
+        };
+    }
+
+    pub(crate) fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+    }
+
+    pub(crate) fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// When the result of `update` may change without a new input event.
+    pub(crate) fn deadline(&self, config: &Config) -> Option<Instant> {
+        let press = self
+            .held_since
+            .iter()
+            .filter(|(action, _)| !self.down.contains(action))
+            .map(|(_, since)| *since + Duration::from_millis(config.press_delay_ms))
+            .min();
+        match (press, self.tail_until) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Feed the actions of the bindings held now and return whether the mic should be on.
+    pub(crate) fn update(&mut self, config: &Config, held: &[Action]) -> bool {
+        let now = Instant::now();
+        self.held_since.retain(|action, _| held.contains(action));
+        for action in held {
+            self.held_since.entry(*action).or_insert(now);
+        }
+        // Brushing the key shorter than the press delay never counts as a press.
+        let delay = Duration::from_millis(config.press_delay_ms);
+        let down: HashSet<Action> = self
+            .held_since
+            .iter()
+            .filter(|(_, since)| now - **since >= delay)
+            .map(|(action, _)| *action)
+            .collect();
+        let pressed = |action| down.contains(&action) && !self.down.contains(&action);
+        if pressed(Action::Toggle) {
+            // Toggle away from the startup state, not from the latch underneath it.
+            if let Some(on) = self.startup.take() {
+                self.latched = on != config.reverse;
+            }
+            self.latched = !self.latched;
+        }
+        if pressed(Action::MuteLock) {
+            self.locked = !self.locked;
+        }
+        let trigger_on = self.desired(config, &down);
+        let forced_off = self.locked || down.contains(&Action::Mute);
+        let want_on = down.contains(&Action::Talk) || trigger_on;
+        self.down = down;
+
+        self.on = if forced_off {
+            // Muting on purpose skips the release delay.
+            self.tail_until = None;
+            false
+        } else if want_on {
+            self.tail_until = None;
+            true
+        } else if self.on && config.release_delay_ms > 0 {
+            let until = *self
+                .tail_until
+                .get_or_insert(now + Duration::from_millis(config.release_delay_ms));
+            if now < until {
+                true
+            } else {
+                self.tail_until = None;
+                false
+            }
+        } else {
+            self.tail_until = None;
+            false
+        };
+        self.on
+    }
+
+    /// Mic state from the `trigger` bindings and the latch.
+    fn desired(&mut self, config: &Config, down: &HashSet<Action>) -> bool {
+        let chord_down = down.contains(&Action::Trigger);
+        let was_down = self.down.contains(&Action::Trigger);
+        let pressed = chord_down && !was_down;
+        let released = !chord_down && was_down;
+        match config.trigger {
+            Trigger::Hold => {
+                if pressed {
+                    self.startup = None;
+                }
+                self.startup
+                    .unwrap_or((chord_down || self.latched) != config.reverse)
+            }
+            Trigger::Toggle => {
+                if pressed {
+                    self.latched = !self.latched;
+                }
+                self.latched
+            }
+            Trigger::Hybrid => {
+                if pressed {
+                    self.press = Some((Instant::now(), self.latched));
+                }
+                if released {
+                    if let Some((started, was_latched)) = self.press.take() {
+                        let tap =
+                            started.elapsed() < Duration::from_millis(config.tap_threshold_ms);
+                        // A tap flips the latch; releasing a long hold always mutes.
+                        self.latched = tap && !was_latched;
+                    }
+                }
+                chord_down || self.latched
+            }
+        }
+    }
+}
+
+/// Mic state applied at launch for the configured trigger.
+pub(crate) fn startup_state(config: &Config) -> StartupState {
+    match config.trigger {
+        Trigger::Hold => config.startup_state,
+        Trigger::Toggle | Trigger::Hybrid => config.toggle_startup_state,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{config_from_persisted, PersistedConfig};
+    use std::thread::sleep;
+
+    fn config(trigger: Trigger) -> Config {
+        let mut config = config_from_persisted(PersistedConfig::default()).unwrap();
+        config.trigger = trigger;
+        config
+    }
+
+    /// Wait until `deadline` says the state may change, then update again.
+    fn settle(state: &mut TriggerState, config: &Config, held: &[Action]) -> bool {
+        let deadline = state.deadline(config).expect("a pending deadline");
+        sleep(deadline.saturating_duration_since(Instant::now()) + Duration::from_millis(5));
+        state.update(config, held)
+    }
+
+    #[test]
+    fn hold_follows_trigger() {
+        let config = config(Trigger::Hold);
+        let mut state = TriggerState::new(&config);
+        assert!(!state.update(&config, &[]));
+        assert!(state.update(&config, &[Action::Trigger]));
+        assert!(!state.update(&config, &[]));
+    }
+
+    #[test]
+    fn reverse_hold_mutes_while_held() {
+        let mut config = config(Trigger::Hold);
+        config.reverse = true;
+        config.startup_state = StartupState::Unmuted;
+        let mut state = TriggerState::new(&config);
+        assert!(state.update(&config, &[]));
+        assert!(!state.update(&config, &[Action::Trigger]));
+        assert!(state.update(&config, &[]));
+    }
+
+    #[test]
+    fn toggle_flips_on_each_press() {
+        let config = config(Trigger::Toggle);
+        let mut state = TriggerState::new(&config);
+        assert!(state.update(&config, &[Action::Trigger]));
+        assert!(state.update(&config, &[]));
+        assert!(!state.update(&config, &[Action::Trigger]));
+        assert!(!state.update(&config, &[]));
+    }
+
+    #[test]
+    fn hybrid_tap_latches_and_hold_talks() {
+        let mut config = config(Trigger::Hybrid);
+        config.tap_threshold_ms = 30;
+        let mut state = TriggerState::new(&config);
+        // Tap: latched on until the next tap.
+        assert!(state.update(&config, &[Action::Trigger]));
+        assert!(state.update(&config, &[]));
+        assert!(state.update(&config, &[Action::Trigger]));
+        assert!(!state.update(&config, &[]));
+        // Hold longer than the threshold: on only while held.
+        assert!(state.update(&config, &[Action::Trigger]));
+        sleep(Duration::from_millis(40));
+        assert!(!state.update(&config, &[]));
+    }
+
+    #[test]
+    fn mute_lock_beats_push_to_mute() {
+        let config = config(Trigger::Hold);
+        let mut state = TriggerState::new(&config);
+        state.update(&config, &[Action::MuteLock]);
+        assert!(!state.update(&config, &[Action::Mute]));
+        // Letting go of push-to-mute leaves the lock in place.
+        assert!(!state.update(&config, &[]));
+        assert!(state.is_locked());
+    }
+
+    #[test]
+    fn mute_lock_beats_push_to_talk() {
+        let config = config(Trigger::Hold);
+        let mut state = TriggerState::new(&config);
+        state.update(&config, &[Action::MuteLock]);
+        state.update(&config, &[]);
+        assert!(!state.update(&config, &[Action::Talk]));
+        assert!(!state.update(&config, &[Action::Talk, Action::Trigger]));
+        // A second press unlocks; the held push-to-talk counts again.
+        state.update(&config, &[Action::Talk]);
+        assert!(state.update(&config, &[Action::Talk, Action::MuteLock]));
+        assert!(!state.is_locked());
+    }
+
+    #[test]
+    fn mute_lock_beats_toggle() {
+        let config = config(Trigger::Toggle);
+        let mut state = TriggerState::new(&config);
+        assert!(state.update(&config, &[Action::Toggle]));
+        state.update(&config, &[]);
+        assert!(!state.update(&config, &[Action::MuteLock]));
+        state.update(&config, &[]);
+        // The latch is still on underneath and comes back once unlocked.
+        assert!(!state.update(&config, &[]));
+        assert!(state.update(&config, &[Action::MuteLock]));
+    }
+
+    #[test]
+    fn reset_keeps_mute_lock() {
+        let config = config(Trigger::Toggle);
+        let mut state = TriggerState::new(&config);
+        state.update(&config, &[Action::MuteLock]);
+        state.reset();
+        assert!(state.is_locked());
+        assert!(!state.update(&config, &[Action::Trigger]));
+        state.update(&config, &[]);
+        assert!(state.update(&config, &[Action::MuteLock]));
+    }
+
+    #[test]
+    fn push_to_mute_beats_push_to_talk() {
+        let config = config(Trigger::Hold);
+        let mut state = TriggerState::new(&config);
+        assert!(state.update(&config, &[Action::Talk]));
+        assert!(!state.update(&config, &[Action::Talk, Action::Mute]));
+        assert!(state.update(&config, &[Action::Talk]));
+    }
+
+    #[test]
+    fn push_to_mute_beats_trigger_and_toggle() {
+        let config = config(Trigger::Toggle);
+        let mut state = TriggerState::new(&config);
+        assert!(state.update(&config, &[Action::Trigger]));
+        state.update(&config, &[]);
+        assert!(!state.update(&config, &[Action::Mute]));
+        assert!(state.update(&config, &[]));
+    }
+
+    #[test]
+    fn push_to_talk_beats_reverse_trigger() {
+        let mut config = config(Trigger::Hold);
+        config.reverse = true;
+        let mut state = TriggerState::new(&config);
+        assert!(!state.update(&config, &[Action::Trigger]));
+        assert!(state.update(&config, &[Action::Trigger, Action::Talk]));
+    }
+
+    #[test]
+    fn push_to_talk_beats_toggled_off() {
+        let config = config(Trigger::Toggle);
+        let mut state = TriggerState::new(&config);
+        assert!(!state.update(&config, &[]));
+        assert!(state.update(&config, &[Action::Talk]));
+        assert!(!state.update(&config, &[]));
+    }
+
+    #[test]
+    fn release_tail_keeps_mic_on() {
+        let mut config = config(Trigger::Hold);
+        config.release_delay_ms = 30;
+        let mut state = TriggerState::new(&config);
+        assert!(state.update(&config, &[Action::Trigger]));
+        assert!(state.update(&config, &[]));
+        assert!(!settle(&mut state, &config, &[]));
+        assert_eq!(state.deadline(&config), None);
+    }
+
+    #[test]
+    fn pressing_again_during_tail_keeps_mic_on() {
+        let mut config = config(Trigger::Hold);
+        config.release_delay_ms = 30;
+        let mut state = TriggerState::new(&config);
+        state.update(&config, &[Action::Trigger]);
+        assert!(state.update(&config, &[]));
+        assert!(state.update(&config, &[Action::Trigger]));
+        sleep(Duration::from_millis(40));
+        assert!(state.update(&config, &[Action::Trigger]));
+    }
+
+    #[test]
+    fn muting_skips_release_tail() {
+        let mut config = config(Trigger::Hold);
+        config.release_delay_ms = 1000;
+        let mut state = TriggerState::new(&config);
+        state.update(&config, &[Action::Trigger]);
+        assert!(state.update(&config, &[]));
+        assert!(!state.update(&config, &[Action::Mute]));
+        assert_eq!(state.deadline(&config), None);
+    }
+
+    #[test]
+    fn press_delay_postpones_press() {
+        let mut config = config(Trigger::Hold);
+        config.press_delay_ms = 30;
+        let mut state = TriggerState::new(&config);
+        assert!(!state.update(&config, &[Action::Trigger]));
+        assert!(settle(&mut state, &config, &[Action::Trigger]));
+        assert!(!state.update(&config, &[]));
+    }
+
+    #[test]
+    fn press_shorter_than_delay_is_ignored() {
+        let mut config = config(Trigger::Toggle);
+        config.press_delay_ms = 1000;
+        let mut state = TriggerState::new(&config);
+        assert!(!state.update(&config, &[Action::Trigger]));
+        assert!(!state.update(&config, &[]));
+        assert_eq!(state.deadline(&config), None);
+        assert!(!state.update(&config, &[Action::Toggle]));
+        assert!(!state.update(&config, &[]));
+    }
+
+    #[test]
+    fn hold_startup_state_lasts_until_first_press() {
+        let mut config = config(Trigger::Hold);
+        config.startup_state = StartupState::Unmuted;
+        let mut state = TriggerState::new(&config);
+        assert!(state.update(&config, &[]));
+        assert!(state.update(&config, &[Action::Trigger]));
+        assert!(!state.update(&config, &[]));
+    }
+}
@@ -0,0 +1,163 @@
+//! `pttkey watch`: stream the mic state to a status bar (waybar, i3blocks, polybar), one line
+//! per change.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+
+use crate::client::connect;
+use crate::config::{backup_config_path, config_path, read_persisted_config, PersistedConfig};
+use crate::control::Status;
+
+/// How long to wait before looking for a daemon again.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy)]
+enum Format {
+    Waybar,
+    I3blocks,
+    Plain,
+}
+
+/// What `render` shows for each state, from the `icon_*` settings.
+struct Icons {
+    on: String,
+    off: String,
+    locked: String,
+    missing: String,
+}
+
+/// Read the icons from the config file, falling back to the defaults. Unlike the daemon,
+/// this never creates or rewrites the file.
+fn load_icons() -> Icons {
+    let path = [config_path(), backup_config_path()]
+        .into_iter()
+        .flatten()
+        .find(|path| path.exists());
+    let config = match path.map(|path| read_persisted_config(&path)) {
+        Some(Ok(config)) => config,
+        Some(Err(err)) => {
+            eprintln!("Using the default icons: {err:#}");
+            PersistedConfig::default()
+        }
+        None => PersistedConfig::default(),
+    };
+    Icons {
+        on: config.icon_on,
+        off: config.icon_off,
+        locked: config.icon_locked,
+        missing: config.icon_missing,
+    }
+}
+
+/// A reply or status event from the control socket.
+#[derive(Deserialize)]
+struct Message {
+    status: Option<Status>,
+    error: Option<String>,
+}
+
+fn parse_format(value: &str) -> Result<Format> {
+    match value.to_ascii_lowercase().as_str() {
+        "waybar" => Ok(Format::Waybar),
+        "i3blocks" => Ok(Format::I3blocks),
+        "plain" => Ok(Format::Plain),
+        _ => bail!("Invalid --format '{value}'. Use 'waybar', 'i3blocks' or 'plain'."),
+    }
+}
+
+/// Run `pttkey watch [--format waybar|i3blocks|plain]` until stdout is closed.
+pub(crate) fn run(args: &[String]) -> Result<()> {
+    let mut format = Format::Plain;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--format" => {
+                i += 1;
+                let value = args.get(i).context("missing value for --format")?;
+                format = parse_format(value)?;
+            }
+            other => bail!("Unknown argument '{other}' for pttkey watch"),
+        }
+        i += 1;
+    }
+
+    let icons = load_icons();
+    let mut last = None;
+    loop {
+        match connect() {
+            Ok(stream) => follow(stream, &icons, format, &mut last)?,
+            Err(_) => emit(&render(&icons, format, None), &mut last)?,
+        }
+        thread::sleep(RECONNECT_INTERVAL);
+    }
+}
+
+/// Subscribe and print every status until the daemon goes away.
+fn follow(
+    mut stream: UnixStream,
+    icons: &Icons,
+    format: Format,
+    last: &mut Option<String>,
+) -> Result<()> {
+    if stream.write_all(b"{\"command\":\"subscribe\"}\n").is_err() {
+        return Ok(());
+    }
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(message) = serde_json::from_str::<Message>(&line) else {
+            continue;
+        };
+        if let Some(error) = message.error {
+            eprintln!("pttkey: {error}");
+        }
+        if let Some(status) = message.status {
+            emit(&render(icons, format, Some(&status)), last)?;
+        }
+    }
+    emit(&render(icons, format, None), last)
+}
+
+/// Print `line` unless it repeats the previous one; fails once stdout is closed.
+fn emit(line: &str, last: &mut Option<String>) -> Result<()> {
+    if last.as_deref() == Some(line) {
+        return Ok(());
+    }
+    writeln!(std::io::stdout().lock(), "{line}").context("Failed to write to stdout")?;
+    *last = Some(line.to_string());
+    Ok(())
+}
+
+/// One output line; `None` means pttkey is not running.
+fn render(icons: &Icons, format: Format, status: Option<&Status>) -> String {
+    let (class, icon, tooltip) = match status {
+        None => ("missing", &icons.missing, "pttkey is not running"),
+        Some(status) if status.devices.is_empty() => ("missing", &icons.missing, "No input device"),
+        Some(status) if status.locked => ("locked", &icons.locked, "Mic locked muted"),
+        Some(status) if status.active => ("on", &icons.on, "Mic on"),
+        Some(_) => ("off", &icons.off, "Mic muted"),
+    };
+    match format {
+        Format::Waybar => json!({
+            "text": icon,
+            "alt": class,
+            "class": class,
+            "tooltip": tooltip,
+        })
+        .to_string(),
+        Format::I3blocks => json!({
+            "full_text": icon,
+            "short_text": icon,
+            "class": class,
+            "tooltip": tooltip,
+        })
+        .to_string(),
+        Format::Plain => format!("{icon} {tooltip}"),
+    }
+}
@@ -0,0 +1,211 @@
+//! Runs pttkey against a private session bus and drives it over D-Bus.
+//!
+//! Ignored by default since it needs `dbus-daemon` and a `/dev/input` to watch; run it with
+//! `cargo test --test dbus -- --ignored`.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use zbus::blocking::fdo::DBusProxy;
+use zbus::blocking::proxy::Builder as ProxyBuilder;
+use zbus::blocking::{connection, Connection, Proxy};
+use zbus::proxy::CacheProperties;
+
+const DBUS_NAME: &str = "io.github.wuild.PttKey";
+const DBUS_PATH: &str = "/io/github/wuild/PttKey";
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stands in for wpctl: keeps the volume and mute state of the default source in a file.
+const WPCTL_STUB: &str = r#"#!/bin/sh
+state="$(dirname "$0")/state"
+case "$1" in
+set-volume) echo "Volume: $3" > "$state" ;;
+set-mute) if [ "$3" = 1 ]; then echo "Volume: 1.00 [MUTED]" > "$state"; else echo "Volume: 1.00" > "$state"; fi ;;
+get-volume) cat "$state" 2>/dev/null || echo "Volume: 1.00" ;;
+esac
+"#;
+
+const CONFIG: &str = r#"backend = "wpctl"
+sounds = false
+# Never present, so pttkey waits for it without touching real devices.
+device_path = "/dev/input/pttkey-test-missing"
+
+[notifications]
+device = false
+backend = false
+config = false
+mic = false
+"#;
+
+/// Kills the process when the test ends, also on a failed assertion.
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Temporary directory removed when the test ends.
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn dbus_daemon_available() -> bool {
+    Command::new("dbus-daemon")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Start a private session bus and return it with its address.
+fn start_bus() -> (KillOnDrop, String) {
+    let mut child = Command::new("dbus-daemon")
+        .args(["--session", "--nofork", "--print-address"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start dbus-daemon");
+    let stdout = child.stdout.take().unwrap();
+    let child = KillOnDrop(child);
+    let mut address = String::new();
+    BufReader::new(stdout)
+        .read_line(&mut address)
+        .expect("failed to read the bus address");
+    (child, address.trim().to_string())
+}
+
+fn write_executable(path: &Path, contents: &str) {
+    fs::write(path, contents).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn wait_for_name(connection: &Connection) {
+    let dbus = DBusProxy::new(connection).unwrap();
+    let deadline = Instant::now() + TIMEOUT;
+    while !dbus.name_has_owner(DBUS_NAME.try_into().unwrap()).unwrap() {
+        assert!(
+            Instant::now() < deadline,
+            "pttkey did not claim {DBUS_NAME}"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn send_control(runtime_dir: &Path, command: &str) {
+    let mut stream = UnixStream::connect(runtime_dir.join("pttkey.sock")).unwrap();
+    writeln!(stream, "{{\"command\":\"{command}\"}}").unwrap();
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).unwrap();
+    assert!(reply.contains("\"ok\":true"), "{command} failed: {reply}");
+}
+
+#[test]
+#[ignore = "needs dbus-daemon and /dev/input"]
+fn dbus_service() {
+    assert!(dbus_daemon_available(), "dbus-daemon is not installed");
+    assert!(
+        Path::new("/dev/input").is_dir(),
+        "/dev/input does not exist"
+    );
+
+    let dir = TempDir(std::env::temp_dir().join(format!("pttkey-dbus-{}", std::process::id())));
+    let home = dir.0.join("home");
+    let runtime_dir = dir.0.join("run");
+    let bin_dir = dir.0.join("bin");
+    for path in [
+        home.join(".config/pttkey"),
+        runtime_dir.clone(),
+        bin_dir.clone(),
+    ] {
+        fs::create_dir_all(path).unwrap();
+    }
+    fs::set_permissions(&runtime_dir, fs::Permissions::from_mode(0o700)).unwrap();
+    fs::write(home.join(".config/pttkey/config.toml"), CONFIG).unwrap();
+    write_executable(&bin_dir.join("wpctl"), WPCTL_STUB);
+
+    let (_bus, address) = start_bus();
+    let path = format!(
+        "{}:{}",
+        bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let _pttkey = KillOnDrop(
+        // Only the test's own environment, so the user's real config and mic stay untouched.
+        Command::new(env!("CARGO_BIN_EXE_pttkey"))
+            .env_clear()
+            .env("HOME", &home)
+            .env("XDG_CONFIG_HOME", home.join(".config"))
+            .env("XDG_RUNTIME_DIR", &runtime_dir)
+            .env("DBUS_SESSION_BUS_ADDRESS", &address)
+            .env("PATH", path)
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("failed to start pttkey"),
+    );
+
+    let connection = connection::Builder::address(address.as_str())
+        .unwrap()
+        .build()
+        .unwrap();
+    wait_for_name(&connection);
+    let proxy: Proxy = ProxyBuilder::new(&connection)
+        .destination(DBUS_NAME)
+        .unwrap()
+        .path(DBUS_PATH)
+        .unwrap()
+        .interface(DBUS_NAME)
+        .unwrap()
+        .cache_properties(CacheProperties::No)
+        .build()
+        .unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let signals = proxy.receive_signal("StateChanged").unwrap();
+    thread::spawn(move || {
+        for signal in signals {
+            let state: (bool, bool) = signal.body().deserialize().unwrap();
+            if tx.send(state).is_err() {
+                break;
+            }
+        }
+    });
+    let active = || proxy.get_property::<bool>("Active").unwrap();
+    let locked = || proxy.get_property::<bool>("Locked").unwrap();
+
+    assert!(!active());
+    assert!(!locked());
+
+    let _: () = proxy.call("SetActive", &(true,)).unwrap();
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), (true, false));
+    assert!(active());
+
+    let _: () = proxy.call("Toggle", &()).unwrap();
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), (false, false));
+    assert!(!active());
+
+    let _: () = proxy.call("Toggle", &()).unwrap();
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), (true, false));
+
+    // A lock from the control socket shows up on the bus as well.
+    send_control(&runtime_dir, "lock-muted");
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), (false, true));
+    assert!(locked());
+    assert!(!active());
+
+    send_control(&runtime_dir, "unlock");
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), (true, false));
+    assert!(!locked());
+}